@@ -0,0 +1,71 @@
+//! Times parsing an array of same-shaped records with and without
+//! `ParserOptions::intern_keys`, and counts the bytes of key text the parser
+//! holds on to. Run with `cargo bench --bench intern_keys`
+
+use json_parser::{Lexer, Parser, ParserOptions, Token};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const RECORDS: usize = 10_000;
+const ROUNDS: u32 = 20;
+
+fn records() -> String {
+    let records: Vec<String> = (0..RECORDS)
+        .map(|i| {
+            format!(
+                r#"{{"id": {}, "name": "user {}", "active": {}, "score": {}.5}}"#,
+                i,
+                i,
+                i % 2 == 0,
+                i % 100
+            )
+        })
+        .collect();
+    format!("[{}]", records.join(","))
+}
+
+fn time_parse(source: &str, options: ParserOptions) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        let mut lexer = Lexer::new(source);
+        lexer.lex().unwrap();
+        let parser = Parser::from_lexer(lexer, options);
+        black_box(parser.parse().unwrap());
+    }
+    start.elapsed() / ROUNDS
+}
+
+// the capacity of every key token, which the parsed keys borrow from
+fn key_bytes(source: &str, options: ParserOptions) -> usize {
+    let mut lexer = Lexer::new(source);
+    lexer.lex().unwrap();
+    let parser = Parser::from_lexer(lexer, options);
+    parser
+        .tokens()
+        .windows(2)
+        .map(|pair| match pair {
+            [Token::Str(key, _), Token::Colon(_)] => key.capacity(),
+            _ => 0,
+        })
+        .sum()
+}
+
+fn main() {
+    let source = records();
+    let interning = ParserOptions {
+        intern_keys: true,
+        ..Default::default()
+    };
+    println!("{} records, {} bytes", RECORDS, source.len());
+    for (name, options) in [
+        ("default", ParserOptions::default()),
+        ("intern_keys", interning),
+    ] {
+        println!(
+            "{:<12} {:?} per parse, {} bytes of keys held",
+            name,
+            time_parse(&source, options),
+            key_bytes(&source, options)
+        );
+    }
+}
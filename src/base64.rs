@@ -0,0 +1,75 @@
+use crate::parser::Value;
+use alloc::vec::Vec;
+
+/// Value of a character of the standard base64 alphabet (RFC 4648)
+fn sextet(c: u8) -> Option<u32> {
+    match c {
+        b'A'..=b'Z' => Some((c - b'A') as u32),
+        b'a'..=b'z' => Some((c - b'a' + 26) as u32),
+        b'0'..=b'9' => Some((c - b'0' + 52) as u32),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
+
+/// Decodes standard base64, with or without the trailing `=` padding. Written
+/// by hand so that the feature doesn't pull in a dependency
+fn decode(input: &str) -> Option<Vec<u8>> {
+    let bytes = input.as_bytes();
+    let data = match bytes.len() % 4 {
+        // padding is only allowed when it completes the last group
+        0 if bytes.ends_with(b"==") => &bytes[..bytes.len() - 2],
+        0 if bytes.ends_with(b"=") => &bytes[..bytes.len() - 1],
+        1 => return None,
+        _ => bytes,
+    };
+    let mut output = Vec::with_capacity(data.len() * 3 / 4);
+    for group in data.chunks(4) {
+        let mut bits = 0;
+        for &c in group {
+            bits = bits << 6 | sextet(c)?;
+        }
+        // the groups of 2 and 3 characters at the end hold 1 and 2 bytes
+        bits <<= 6 * (4 - group.len());
+        let decoded = bits.to_be_bytes();
+        output.extend_from_slice(&decoded[1..group.len()]);
+    }
+    Some(output)
+}
+
+impl Value<'_> {
+    /// Decodes a string holding standard base64, `None` if the value isn't a
+    /// string or isn't valid base64. Needs the `base64` feature
+    pub fn as_bytes_base64(&self) -> Option<Vec<u8>> {
+        match self {
+            Value::Str(s) => decode(s),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_as_bytes_base64() {
+    let value = crate::parse_str(r#"["aGVsbG8gd29ybGQ=", "AAEC/w==", "YWI", "", 1]"#).unwrap();
+    assert_eq!(
+        value.at(0).unwrap().as_bytes_base64(),
+        Some(b"hello world".to_vec())
+    );
+    assert_eq!(
+        value.at(1).unwrap().as_bytes_base64(),
+        Some(vec![0, 1, 2, 255])
+    );
+    assert_eq!(value.at(2).unwrap().as_bytes_base64(), Some(b"ab".to_vec()));
+    assert_eq!(value.at(3).unwrap().as_bytes_base64(), Some(Vec::new()));
+    assert_eq!(value.at(4).unwrap().as_bytes_base64(), None);
+
+    for invalid in ["aGVsbG8*", "a", "ab=c", "=abc", "a==="] {
+        assert_eq!(
+            Value::Str(invalid.into()).as_bytes_base64(),
+            None,
+            "{}",
+            invalid
+        );
+    }
+}
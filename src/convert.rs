@@ -0,0 +1,208 @@
+use crate::parser::{OwnedValue, Value};
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// Conversion from a borrowed `Value` into a plain Rust type
+pub trait FromValue: Sized {
+    fn from_value(value: &Value<'_>) -> Result<Self, FromValueError>;
+}
+
+#[derive(Debug, PartialEq)]
+pub enum FromValueError {
+    /// The value isn't of the `expected` type
+    TypeMismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// The number isn't an integer representable by the target type
+    NotAnInteger(f64),
+    /// The element at `index` of an array couldn't be converted
+    AtIndex {
+        index: usize,
+        error: Box<FromValueError>,
+    },
+}
+
+impl core::fmt::Display for FromValueError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            FromValueError::TypeMismatch { expected, found } => {
+                write!(f, "Expected {}, got {}", expected, found)
+            }
+            FromValueError::NotAnInteger(n) => write!(f, "Expected an integer, got {}", n),
+            FromValueError::AtIndex { index, error } => {
+                write!(f, "{} at index: {}", error, index)
+            }
+        }
+    }
+}
+
+impl core::error::Error for FromValueError {}
+
+fn mismatch(expected: &'static str, value: &Value<'_>) -> FromValueError {
+    FromValueError::TypeMismatch {
+        expected,
+        found: value.type_name(),
+    }
+}
+
+impl FromValue for f64 {
+    fn from_value(value: &Value<'_>) -> Result<Self, FromValueError> {
+        match value {
+            Value::Number(n) => Ok(*n),
+            other => Err(mismatch("number", other)),
+        }
+    }
+}
+
+impl FromValue for i64 {
+    fn from_value(value: &Value<'_>) -> Result<Self, FromValueError> {
+        match value {
+            // `i64::MAX as f64` rounds up to 2^63, hence the exclusive upper bound.
+            // `% 1.0` instead of `fract`, which isn't available without `std`
+            Value::Number(n) if n % 1.0 == 0.0 && *n >= i64::MIN as f64 && *n < i64::MAX as f64 => {
+                Ok(*n as i64)
+            }
+            Value::Number(n) => Err(FromValueError::NotAnInteger(*n)),
+            other => Err(mismatch("number", other)),
+        }
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: &Value<'_>) -> Result<Self, FromValueError> {
+        match value {
+            Value::Bool(b) => Ok(*b),
+            other => Err(mismatch("bool", other)),
+        }
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: &Value<'_>) -> Result<Self, FromValueError> {
+        match value {
+            Value::Str(s) => Ok(s.to_string()),
+            other => Err(mismatch("string", other)),
+        }
+    }
+}
+
+impl FromValue for OwnedValue {
+    fn from_value(value: &Value<'_>) -> Result<Self, FromValueError> {
+        Ok(value.clone().into_owned())
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    /// `null` becomes `None`, anything else is converted with `T`
+    fn from_value(value: &Value<'_>) -> Result<Self, FromValueError> {
+        match value {
+            Value::Null => Ok(None),
+            other => T::from_value(other).map(Some),
+        }
+    }
+}
+
+/// Appends `field` to a CSV row, in double quotes if it contains a comma, a quote
+/// or a line break, with the quotes inside doubled (RFC 4180)
+fn push_csv_field(row: &mut String, field: &str) {
+    if field.contains([',', '"', '\r', '\n']) {
+        row.push('"');
+        row.push_str(&field.replace('"', "\"\""));
+        row.push('"');
+    } else {
+        row.push_str(field);
+    }
+}
+
+/// Writes `values`, which must be flat objects, as CSV with a header row of
+/// `columns` and one row per object, all ending in CRLF (RFC 4180). Missing and
+/// `null` fields are empty, strings are written without their json quotes and
+/// numbers and bools as in json. A value that isn't an object, or a field
+/// holding an object or an array, fails with the index of its row
+pub fn to_csv(values: &[Value<'_>], columns: &[&str]) -> Result<String, FromValueError> {
+    let mut csv = String::new();
+    let mut push_row = |fields: Vec<String>| {
+        for (i, field) in fields.iter().enumerate() {
+            if i > 0 {
+                csv.push(',');
+            }
+            push_csv_field(&mut csv, field);
+        }
+        csv.push_str("\r\n");
+    };
+    push_row(columns.iter().map(|column| column.to_string()).collect());
+    for (index, value) in values.iter().enumerate() {
+        let at_index = |error| FromValueError::AtIndex {
+            index,
+            error: Box::new(error),
+        };
+        let Value::Dict(map) = value else {
+            return Err(at_index(mismatch("object", value)));
+        };
+        let fields = columns
+            .iter()
+            .map(|column| match map.get(column) {
+                None | Some(Value::Null) => Ok(String::new()),
+                Some(Value::Str(s)) => Ok(s.to_string()),
+                Some(field @ (Value::Number(_) | Value::Bool(_))) => Ok(field.to_string()),
+                Some(field) => Err(at_index(mismatch("scalar", field))),
+            })
+            .collect::<Result<_, _>>()?;
+        push_row(fields);
+    }
+    Ok(csv)
+}
+
+#[test]
+fn test_from_value() {
+    assert_eq!(f64::from_value(&Value::Number(1.5)), Ok(1.5));
+    assert_eq!(i64::from_value(&Value::Number(-3.0)), Ok(-3));
+    assert_eq!(
+        i64::from_value(&Value::Number(3.5)),
+        Err(FromValueError::NotAnInteger(3.5))
+    );
+    assert_eq!(
+        String::from_value(&Value::Str("test".into())),
+        Ok("test".to_string())
+    );
+    assert_eq!(Option::<bool>::from_value(&Value::Null), Ok(None));
+    assert_eq!(
+        bool::from_value(&Value::Null),
+        Err(FromValueError::TypeMismatch {
+            expected: "bool",
+            found: "null"
+        })
+    );
+}
+
+#[test]
+fn test_to_csv() {
+    let rows = crate::parse_str(
+        r#"[{"name": "Alice", "age": 30, "note": "says \"hi\", twice"},
+            {"age": 25.5, "name": "Bob", "note": null, "extra": true}]"#,
+    )
+    .unwrap();
+    let Value::List(rows) = rows else {
+        panic!("expected a list")
+    };
+    assert_eq!(
+        to_csv(&rows, &["name", "age", "note", "missing"]).unwrap(),
+        "name,age,note,missing\r\nAlice,30,\"says \"\"hi\"\", twice\",\r\nBob,25.5,,\r\n"
+    );
+
+    let rows = [Value::new_object(), Value::Number(1.0)];
+    assert_eq!(
+        to_csv(&rows, &["a"]),
+        Err(FromValueError::AtIndex {
+            index: 1,
+            error: Box::new(FromValueError::TypeMismatch {
+                expected: "object",
+                found: "number"
+            })
+        })
+    );
+    let rows = [crate::parse_str(r#"{"a": [1]}"#).unwrap()];
+    assert!(to_csv(&rows, &["a"]).is_err());
+}
@@ -0,0 +1,102 @@
+use crate::error::JsonError;
+use crate::parser::OwnedValue;
+use alloc::borrow::Cow;
+use alloc::string::String;
+
+/// Byte order of UTF-16 input
+#[derive(Clone, Copy)]
+enum ByteOrder {
+    Little,
+    Big,
+}
+
+/// Decodes UTF-16 in `order` into a string. A stray last byte is decoded as a
+/// lone surrogate, so it's reported like any other invalid UTF-16
+fn decode_utf16(bytes: &[u8], order: ByteOrder) -> Result<String, JsonError> {
+    let units = bytes.chunks(2).map(|pair| match (pair, order) {
+        (&[a, b], ByteOrder::Little) => u16::from_le_bytes([a, b]),
+        (&[a, b], ByteOrder::Big) => u16::from_be_bytes([a, b]),
+        _ => 0xD800,
+    });
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .map_err(JsonError::Utf16)
+}
+
+/// Turns `bytes` into UTF-8 text, only copying them when they aren't UTF-8
+/// already:
+/// - a byte order mark selects UTF-8, UTF-16LE or UTF-16BE and is dropped
+/// - without one, UTF-16 is recognized by a zero byte next to the first
+///   character, since json text starts with an ASCII character (RFC 4627)
+/// - anything else is UTF-8 if it's valid, and Latin-1 (ISO 8859-1) otherwise.
+///   Every byte is valid Latin-1, so this never fails, but Windows-1252 text
+///   gets the wrong characters for the bytes 0x80 to 0x9F
+fn transcode(bytes: &[u8]) -> Result<Cow<'_, str>, JsonError> {
+    match bytes {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => Ok(Cow::Borrowed(core::str::from_utf8(rest)?)),
+        [0xFF, 0xFE, rest @ ..] => decode_utf16(rest, ByteOrder::Little).map(Cow::Owned),
+        [0xFE, 0xFF, rest @ ..] => decode_utf16(rest, ByteOrder::Big).map(Cow::Owned),
+        [0, b, ..] if *b != 0 => decode_utf16(bytes, ByteOrder::Big).map(Cow::Owned),
+        [a, 0, ..] if *a != 0 => decode_utf16(bytes, ByteOrder::Little).map(Cow::Owned),
+        _ => match core::str::from_utf8(bytes) {
+            Ok(text) => Ok(Cow::Borrowed(text)),
+            Err(_) => Ok(Cow::Owned(bytes.iter().map(|&b| char::from(b)).collect())),
+        },
+    }
+}
+
+/// Parses a document that may not be UTF-8, as legacy files often aren't even
+/// though json must be. UTF-16 is detected from the byte order mark or the zero
+/// bytes around the first character, and input that isn't valid UTF-8 is read
+/// as Latin-1. The text is transcoded to UTF-8 before lexing, invalid UTF-16
+/// fails with [`JsonError::Utf16`]
+pub fn parse_any_encoding(bytes: &[u8]) -> Result<OwnedValue, JsonError> {
+    crate::parse_str(&transcode(bytes)?)
+}
+
+#[cfg(test)]
+fn utf16(text: &str, order: ByteOrder) -> alloc::vec::Vec<u8> {
+    text.encode_utf16()
+        .flat_map(|unit| match order {
+            ByteOrder::Little => unit.to_le_bytes(),
+            ByteOrder::Big => unit.to_be_bytes(),
+        })
+        .collect()
+}
+
+#[test]
+fn test_parse_any_encoding() {
+    let expected = crate::parse_str(r#"{"name": "Zoë", "emoji": "🦀"}"#).unwrap();
+    let text = r#"{"name": "Zoë", "emoji": "🦀"}"#;
+
+    let mut bytes = alloc::vec![0xFF, 0xFE];
+    bytes.extend(utf16(text, ByteOrder::Little));
+    assert_eq!(parse_any_encoding(&bytes).unwrap(), expected);
+
+    let mut bytes = alloc::vec![0xFE, 0xFF];
+    bytes.extend(utf16(text, ByteOrder::Big));
+    assert_eq!(parse_any_encoding(&bytes).unwrap(), expected);
+
+    // no byte order mark
+    assert_eq!(
+        parse_any_encoding(&utf16(text, ByteOrder::Little)).unwrap(),
+        expected
+    );
+    assert_eq!(parse_any_encoding(text.as_bytes()).unwrap(), expected);
+    let mut bytes = alloc::vec![0xEF, 0xBB, 0xBF];
+    bytes.extend(text.as_bytes());
+    assert_eq!(parse_any_encoding(&bytes).unwrap(), expected);
+
+    assert_eq!(
+        parse_any_encoding(b"[\"caf\xe9\"]").unwrap(),
+        crate::parse_str(r#"["café"]"#).unwrap()
+    );
+
+    let mut bytes = alloc::vec![0xFF, 0xFE];
+    bytes.extend(utf16("[1]", ByteOrder::Little));
+    bytes.push(b' ');
+    assert!(matches!(
+        parse_any_encoding(&bytes),
+        Err(JsonError::Utf16(_))
+    ));
+}
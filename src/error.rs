@@ -0,0 +1,130 @@
+use crate::lexer::LexError;
+use crate::parser::ParseError;
+use alloc::format;
+use alloc::string::{String, ToString};
+
+/// Combines every error that can occur while turning some input into a `Value`,
+/// so callers going through the convenience functions only have one error type
+/// to handle
+#[derive(Debug)]
+pub enum JsonError {
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    Utf8(core::str::Utf8Error),
+    /// Only from [`crate::encoding::parse_any_encoding`]
+    #[cfg(feature = "encoding")]
+    Utf16(core::char::DecodeUtf16Error),
+    Lex(LexError),
+    Parse(ParseError),
+}
+
+impl JsonError {
+    /// Byte offset in the source where the error is, `None` for I/O and UTF-8
+    /// errors and for parse errors from a parser without spans
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            JsonError::Lex(error) => Some(error.offset()),
+            JsonError::Parse(error) => error.offset(),
+            _ => None,
+        }
+    }
+
+    /// The message followed by the offending line of `source` and a caret under
+    /// the column of the error, like rustc does. `source` must be the input that
+    /// produced the error. Without an offset this is just the message
+    pub fn render_with_source(&self, source: &str) -> String {
+        let Some(offset) = self.offset().filter(|&offset| offset <= source.len()) else {
+            return self.to_string();
+        };
+        let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[offset..]
+            .find('\n')
+            .map_or(source.len(), |i| offset + i);
+        let line = source[line_start..line_end].trim_end_matches('\r');
+        let line_number = source[..line_start].matches('\n').count() + 1;
+        let column = source[line_start..offset].chars().count() + 1;
+
+        // tabs are kept so that the caret lines up with the text above it
+        let padding: String = source[line_start..offset]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let gutter = " ".repeat(line_number.to_string().len());
+        format!(
+            "{}\n{} --> {}:{}\n{} |\n{} | {}\n{} | {}^",
+            self, gutter, line_number, column, gutter, line_number, line, gutter, padding
+        )
+    }
+}
+
+impl core::fmt::Display for JsonError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            JsonError::Io(error) => write!(f, "I/O error: {}", error),
+            JsonError::Utf8(error) => write!(f, "Invalid UTF-8: {}", error),
+            #[cfg(feature = "encoding")]
+            JsonError::Utf16(error) => write!(f, "Invalid UTF-16: {}", error),
+            JsonError::Lex(error) => write!(f, "{}", error),
+            JsonError::Parse(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl core::error::Error for JsonError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            JsonError::Io(error) => Some(error),
+            JsonError::Utf8(error) => Some(error),
+            #[cfg(feature = "encoding")]
+            JsonError::Utf16(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for JsonError {
+    fn from(error: std::io::Error) -> Self {
+        JsonError::Io(error)
+    }
+}
+
+impl From<core::str::Utf8Error> for JsonError {
+    fn from(error: core::str::Utf8Error) -> Self {
+        JsonError::Utf8(error)
+    }
+}
+
+impl From<LexError> for JsonError {
+    fn from(error: LexError) -> Self {
+        JsonError::Lex(error)
+    }
+}
+
+impl From<ParseError> for JsonError {
+    fn from(error: ParseError) -> Self {
+        JsonError::Parse(error)
+    }
+}
+
+#[test]
+fn test_render_with_source() {
+    let source = "{\n  \"a\": 1,\n  \"b\": tru\n}";
+    let error = crate::parse_str(source).unwrap_err();
+    assert_eq!(error.offset(), Some(19));
+    assert_eq!(
+        error.render_with_source(source),
+        "Unknown literal 'tru' at line: 3\n  --> 3:8\n  |\n3 |   \"b\": tru\n  |        ^"
+    );
+
+    let source = "[1,\n\t[2, 3,]]";
+    let error = crate::parse_str(source).unwrap_err();
+    let rendered = error.render_with_source(source);
+    assert!(rendered.starts_with("Trailing comma before the closing bracket at line: 2\n"));
+    assert!(rendered.ends_with("2 | \t[2, 3,]]\n  | \t     ^"));
+
+    let error = crate::parse_str("[1 2]").unwrap_err();
+    assert_eq!(error.offset(), Some(3));
+}
@@ -1,3 +1,7 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
 /// For string, number and boolean tokens, the value is also stored along with the
 /// line-no but for all other tokens, only line-no is stored. This line-no is used
 /// for reporting error while parsing
@@ -14,10 +18,39 @@ pub enum Token {
     Bool(bool, usize),
     Null(usize),
     EOF(usize), // End-of-file
+    // only emitted with `LexerOptions::preserve_trivia`
+    Whitespace(String, usize), // whitespace + line-no
+    Comment(String, usize),    // comment including its delimiters + line-no
+}
+
+impl Token {
+    /// Line on which the token starts
+    pub fn line(&self) -> usize {
+        match self {
+            Token::Str(_, line)
+            | Token::Number(_, line)
+            | Token::Bool(_, line)
+            | Token::LeftBracket(line)
+            | Token::RightBracket(line)
+            | Token::LeftBrace(line)
+            | Token::RightBrace(line)
+            | Token::Comma(line)
+            | Token::Colon(line)
+            | Token::Null(line)
+            | Token::EOF(line)
+            | Token::Whitespace(_, line)
+            | Token::Comment(_, line) => *line,
+        }
+    }
+
+    /// Whitespace and comments, which carry no meaning for the parser
+    pub fn is_trivia(&self) -> bool {
+        matches!(self, Token::Whitespace(..) | Token::Comment(..))
+    }
 }
 
-impl std::fmt::Display for Token {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for Token {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match &self {
             Token::Str(s, line) => write!(f, "'{}' at line: {}", &s, line),
             Token::Number(n, line) => write!(f, "'{}' at line: {}", *n, line),
@@ -30,39 +63,271 @@ impl std::fmt::Display for Token {
             Token::Bool(b, line) => write!(f, "'{}' at line: {}", b, line),
             Token::Null(line) => write!(f, "'null' at line: {}", line),
             Token::EOF(line) => write!(f, "'EOF' at line: {}", line),
+            Token::Whitespace(s, line) | Token::Comment(s, line) => {
+                write!(f, "'{}' at line: {}", s.escape_debug(), line)
+            }
         }
     }
 }
 
+/// Extensions to the json grammar, all of them are off by default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LexerOptions {
+    /// Skip `// line` and `/* block */` comments like whitespace
+    pub allow_comments: bool,
+    /// Emit `Whitespace` and `Comment` tokens instead of skipping them, for tools
+    /// like formatters that need to see them. The parser ignores these tokens
+    pub preserve_trivia: bool,
+    /// Keep the backslash of escapes json doesn't define, so `"C:\Users"` gives
+    /// `C:\Users` instead of `C:Users`. `\b` is deliberately treated as unknown
+    /// too, since in pasted paths like `C:\bin` it's hardly ever meant as a
+    /// backspace. The other json escapes, `\f` included, are still decoded
+    pub pass_through_unknown_escapes: bool,
+    /// Record a [`Warning::PrecisionLoss`] for every integer literal that can't
+    /// be stored exactly as `f64`. The token still holds the rounded number
+    pub warn_precision_loss: bool,
+    /// Salvage output of broken producers by lexing bare words other than
+    /// `true`, `false` and `null` as strings, so `{"status": ok}` gives the
+    /// string `ok`. This also makes unquoted keys work
+    pub bare_words_as_strings: bool,
+    /// Accept the JavaScript literals `NaN`, `Infinity` and `-Infinity` for the
+    /// numbers json can't represent. They are still serialized as `null`
+    pub allow_non_finite: bool,
+    /// Lex the JavaScript literal `undefined` as `null`, for data dumped from
+    /// JavaScript objects
+    pub undefined_as_null: bool,
+}
+
+/// Something suspicious in the source that doesn't stop lexing
+#[derive(Debug, PartialEq)]
+pub enum Warning {
+    /// An integer literal, usually beyond 2^53, that was rounded to the
+    /// nearest `f64`
+    PrecisionLoss { line: usize, literal: String },
+}
+
+/// Whether `literal`, parsed as `number`, is an integer that `number` doesn't
+/// represent exactly. Literals with a fraction or exponent aren't checked
+fn loses_precision(literal: &str, number: f64) -> bool {
+    let digits = literal.trim_start_matches(['+', '-']);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return false;
+    }
+    // `{:.0}` prints every digit of an integral f64 exactly
+    let digits = digits.trim_start_matches('0');
+    let exact = format!("{:.0}", number.abs());
+    digits != exact.trim_start_matches('0')
+}
+
+/// Byte range of a token or value in the source, `end` is exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn contains(&self, offset: usize) -> bool {
+        self.start <= offset && offset < self.end
+    }
+}
+
 pub struct Lexer<'a> {
     pub tokens: Vec<Token>,
+    pub spans: Vec<Span>, // span of every token, in the same order
+    pub warnings: Vec<Warning>,
     line: usize,
-    source: &'a str, // json source
+    start_line: usize, // where `line` starts again after `reset`
+    source: &'a str,   // json source
+    options: LexerOptions,
 }
 
+/// Every error holds its message and the byte offset in the source where the
+/// problem is
 #[derive(Debug)]
 pub enum LexError {
-    UnterminatedString(String),
-    UnknownSymbol(String),
-    UnknownLiteral(String),
-    InvalidNumber(String),
+    UnterminatedString(String, usize), // message + offset of the opening quote
+    UnknownSymbol(String, usize),
+    UnknownLiteral(String, usize),
+    InvalidNumber(String, usize), // message + offset of the unexpected character
+    UnterminatedComment(String, usize),
+    InvalidEscape(String, usize), // message + offset of the backslash
+    DanglingSign(String, usize),  // message + offset of the sign
+}
+
+impl LexError {
+    pub fn offset(&self) -> usize {
+        match self {
+            LexError::UnterminatedString(_, offset)
+            | LexError::UnknownSymbol(_, offset)
+            | LexError::UnknownLiteral(_, offset)
+            | LexError::InvalidNumber(_, offset)
+            | LexError::UnterminatedComment(_, offset)
+            | LexError::InvalidEscape(_, offset)
+            | LexError::DanglingSign(_, offset) => *offset,
+        }
+    }
+}
+
+impl core::fmt::Display for LexError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            LexError::UnterminatedString(message, _)
+            | LexError::UnknownSymbol(message, _)
+            | LexError::UnknownLiteral(message, _)
+            | LexError::InvalidNumber(message, _)
+            | LexError::UnterminatedComment(message, _)
+            | LexError::InvalidEscape(message, _)
+            | LexError::DanglingSign(message, _) => write!(f, "{}", message),
+        }
+    }
+}
+
+/// Runs `literal` through the json number grammar (also allowing a leading `+`
+/// like the lexer does) and returns the byte offset of the first character that
+/// doesn't fit, or the length of `literal` if it ends too early
+fn invalid_number_offset(literal: &str) -> usize {
+    #[derive(Clone, Copy, PartialEq)]
+    enum State {
+        Start,
+        Sign,
+        Zero,
+        Integer,
+        Dot,
+        Fraction,
+        Exponent,
+        ExponentSign,
+        ExponentDigits,
+    }
+
+    let mut state = State::Start;
+    for (offset, c) in literal.char_indices() {
+        state = match (state, c) {
+            (State::Start, '+' | '-') => State::Sign,
+            (State::Start | State::Sign, '0') => State::Zero,
+            (State::Start | State::Sign | State::Integer, '0'..='9') => State::Integer,
+            (State::Zero | State::Integer, '.') => State::Dot,
+            (State::Dot | State::Fraction, '0'..='9') => State::Fraction,
+            (State::Zero | State::Integer | State::Fraction, 'e' | 'E') => State::Exponent,
+            (State::Exponent, '+' | '-') => State::ExponentSign,
+            (State::Exponent | State::ExponentSign | State::ExponentDigits, '0'..='9') => {
+                State::ExponentDigits
+            }
+            _ => return offset,
+        };
+    }
+    literal.len()
+}
+
+/// Reads the 4 hex digits of a `\u` escape, `None` if there are fewer or one of
+/// them isn't a hex digit
+fn read_hex4(chars: &mut impl Iterator<Item = (usize, char)>) -> Option<u32> {
+    let mut code = 0;
+    for _ in 0..4 {
+        code = code * 16 + chars.next()?.1.to_digit(16)?;
+    }
+    Some(code)
+}
+
+/// Decodes a `\u` escape whose `\u` was just consumed. Characters outside
+/// the basic plane are written as a surrogate pair of two escapes, a lone
+/// surrogate is invalid
+fn unicode_escape(chars: &mut impl Iterator<Item = (usize, char)>) -> Option<char> {
+    let high = read_hex4(chars)?;
+    if !(0xD800..0xDC00).contains(&high) {
+        return char::from_u32(high);
+    }
+    if chars.next()?.1 != '\\' || chars.next()?.1 != 'u' {
+        return None;
+    }
+    let low = read_hex4(chars)?;
+    if !(0xDC00..0xE000).contains(&low) {
+        return None;
+    }
+    char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(source: &'a str) -> Self {
+        Self::with_options(source, LexerOptions::default())
+    }
+
+    /// For json embedded in a larger document, counts lines from `start_line` so
+    /// that tokens and errors report lines of the enclosing document
+    pub fn new_at(source: &'a str, start_line: usize) -> Self {
+        Self {
+            line: start_line,
+            start_line,
+            ..Self::new(source)
+        }
+    }
+
+    pub fn with_options(source: &'a str, options: LexerOptions) -> Self {
         Self {
             tokens: Vec::new(),
+            spans: Vec::new(),
+            warnings: Vec::new(),
             line: 1,
+            start_line: 1,
             source,
+            options,
         }
     }
 
+    /// Prepares the lexer for another document, keeping the options, the start
+    /// line given to [`Lexer::new_at`] and the capacity of `tokens` and `spans`
+    /// so that lexing many small documents doesn't allocate every time
+    pub fn reset(&mut self, source: &'a str) {
+        self.tokens.clear();
+        self.spans.clear();
+        self.warnings.clear();
+        self.line = self.start_line;
+        self.source = source;
+    }
+
     pub fn lex(&mut self) -> Result<(), LexError> {
+        self.lex_until(false)
+    }
+
+    /// Like [`Lexer::lex`] but stops after the tokens of the first complete
+    /// value, so that whatever follows it isn't looked at. The EOF token is
+    /// pushed right after them
+    pub(crate) fn lex_prefix(&mut self) -> Result<(), LexError> {
+        self.lex_until(true)
+    }
+
+    fn lex_until(&mut self, first_value_only: bool) -> Result<(), LexError> {
         // peekable lets us peek the current character instead of
         // consuming it
         let mut source_iter = self.source.char_indices().peekable();
+        let mut token_start = 0;
+        // nesting depth after the tokens seen so far, for `first_value_only`
+        let mut depth = 0usize;
+        let mut seen = self.tokens.len();
 
         'outer: while let Some((start, current)) = source_iter.next() {
+            // every iteration consumes at most one token, so one pushed by the
+            // previous iteration ends right here
+            self.close_span(token_start, start);
+            token_start = start;
+            if first_value_only && self.tokens.len() > seen {
+                seen = self.tokens.len();
+                let complete = match &self.tokens[seen - 1] {
+                    Token::Whitespace(..) | Token::Comment(..) => false,
+                    Token::LeftBracket(_) | Token::LeftBrace(_) => {
+                        depth += 1;
+                        false
+                    }
+                    Token::RightBracket(_) | Token::RightBrace(_) => {
+                        depth = depth.saturating_sub(1);
+                        depth == 0
+                    }
+                    _ => depth == 0,
+                };
+                if complete {
+                    break;
+                }
+            }
             match current {
                 '[' => self.tokens.push(Token::LeftBracket(self.line)),
                 ']' => self.tokens.push(Token::RightBracket(self.line)),
@@ -71,10 +336,9 @@ impl<'a> Lexer<'a> {
                 ':' => self.tokens.push(Token::Colon(self.line)),
                 ',' => self.tokens.push(Token::Comma(self.line)),
                 '"' => {
-                    // unicode is not handled
                     let string_start = self.line;
                     let mut string = String::new();
-                    while let Some((_, current)) = source_iter.next() {
+                    while let Some((index, current)) = source_iter.next() {
                         if current == '\n' {
                             string.push(current);
                             self.line += 1
@@ -84,7 +348,30 @@ impl<'a> Lexer<'a> {
                                     'n' => string.push('\n'),
                                     't' => string.push('\t'),
                                     'r' => string.push('\r'),
+                                    'f' => string.push('\u{c}'),
+                                    'b' if !self.options.pass_through_unknown_escapes => {
+                                        string.push('\u{8}')
+                                    }
                                     '\\' => string.push('\\'),
+                                    '"' | '/' => string.push(current),
+                                    'u' => match unicode_escape(&mut source_iter) {
+                                        Some(c) => string.push(c),
+                                        None => {
+                                            return Err(LexError::InvalidEscape(
+                                                format!(
+                                                    "Invalid unicode escape at line: {}",
+                                                    self.line
+                                                ),
+                                                index,
+                                            ));
+                                        }
+                                    },
+                                    // keep the backslash of unknown escapes, so
+                                    // pasted paths like `C:\Users` survive
+                                    c if self.options.pass_through_unknown_escapes => {
+                                        string.push('\\');
+                                        string.push(c);
+                                    }
                                     c => string.push(c),
                                 }
                             }
@@ -97,14 +384,95 @@ impl<'a> Lexer<'a> {
                         }
                     }
                     // must have reached EOF, so the string is unterminated
-                    return Err(LexError::UnterminatedString(format!(
-                        "Unterminated string at line: {}",
-                        string_start
-                    )));
+                    return Err(LexError::UnterminatedString(
+                        format!("Unterminated string at line: {}", string_start),
+                        start,
+                    ));
+                }
+                ' ' | '\r' | '\t' | '\n' if self.options.preserve_trivia => {
+                    // one token for the whole run of whitespace
+                    let whitespace_start = self.line;
+                    if current == '\n' {
+                        self.line += 1;
+                    }
+                    while let Some((_, current)) = source_iter.peek() {
+                        if !matches!(current, ' ' | '\r' | '\t' | '\n') {
+                            break;
+                        }
+                        if *current == '\n' {
+                            self.line += 1;
+                        }
+                        source_iter.next().unwrap();
+                    }
+                    let end = if let Some((end, _)) = source_iter.peek() {
+                        *end
+                    } else {
+                        self.source.len()
+                    };
+                    self.tokens.push(Token::Whitespace(
+                        self.source[start..end].to_string(),
+                        whitespace_start,
+                    ));
                 }
                 // skip whitespace
                 ' ' | '\r' | '\t' => continue,
                 '\n' => self.line += 1,
+                // comments are skipped like whitespace, so they are allowed anywhere
+                // including before and after the top-level value
+                '/' if self.options.allow_comments => {
+                    let comment_start = self.line;
+                    let end = match source_iter.next() {
+                        Some((_, '/')) => {
+                            // leave the newline to the main loop so that it's counted
+                            while let Some((_, current)) = source_iter.peek() {
+                                if *current == '\n' {
+                                    break;
+                                }
+                                source_iter.next().unwrap();
+                            }
+                            if let Some((end, _)) = source_iter.peek() {
+                                *end
+                            } else {
+                                self.source.len()
+                            }
+                        }
+                        Some((_, '*')) => {
+                            let mut previous = '\0';
+                            loop {
+                                match source_iter.next() {
+                                    Some((end, '/')) if previous == '*' => break end + 1,
+                                    Some((_, current)) => {
+                                        if current == '\n' {
+                                            self.line += 1;
+                                        }
+                                        previous = current;
+                                    }
+                                    None => {
+                                        return Err(LexError::UnterminatedComment(
+                                            format!(
+                                                "Unterminated comment at line: {}",
+                                                comment_start
+                                            ),
+                                            start,
+                                        ));
+                                    }
+                                }
+                            }
+                        }
+                        _ => {
+                            return Err(LexError::UnknownSymbol(
+                                format!("Unknown symbol / at line: {}", self.line),
+                                start,
+                            ));
+                        }
+                    };
+                    if self.options.preserve_trivia {
+                        self.tokens.push(Token::Comment(
+                            self.source[start..end].to_string(),
+                            comment_start,
+                        ));
+                    }
+                }
                 c if c.is_alphabetic() => {
                     while let Some((_, current)) = source_iter.peek() {
                         if current.is_alphanumeric() || *current == '_' {
@@ -124,14 +492,33 @@ impl<'a> Lexer<'a> {
                         self.tokens.push(Token::Bool(true, self.line));
                     } else if &self.source[start..end] == "false" {
                         self.tokens.push(Token::Bool(false, self.line));
-                    } else if &self.source[start..end] == "null" {
+                    } else if &self.source[start..end] == "null"
+                        || (self.options.undefined_as_null
+                            && &self.source[start..end] == "undefined")
+                    {
                         self.tokens.push(Token::Null(self.line));
+                    } else if self.options.allow_non_finite && &self.source[start..end] == "NaN" {
+                        self.tokens.push(Token::Number(f64::NAN, self.line));
+                    } else if self.options.allow_non_finite
+                        && &self.source[start..end] == "Infinity"
+                    {
+                        self.tokens.push(Token::Number(f64::INFINITY, self.line));
+                    } else if self.options.bare_words_as_strings {
+                        self.tokens
+                            .push(Token::Str(self.source[start..end].to_string(), self.line));
                     } else {
-                        return Err(LexError::UnknownLiteral(format!(
-                            "Unknown literal '{}' at line: {}",
-                            &self.source[start..end],
-                            self.line
-                        )));
+                        let word = &self.source[start..end];
+                        // json literals are lowercase, `True` and `NULL` are common
+                        // slips coming from other formats
+                        let hint = ["true", "false", "null"]
+                            .into_iter()
+                            .find(|literal| literal.eq_ignore_ascii_case(word))
+                            .map(|literal| format!(", did you mean '{}'?", literal))
+                            .unwrap_or_default();
+                        return Err(LexError::UnknownLiteral(
+                            format!("Unknown literal '{}' at line: {}{}", word, self.line, hint),
+                            start,
+                        ));
                     }
                 }
                 c if c.is_numeric() || c == '+' || c == '-' => {
@@ -149,29 +536,89 @@ impl<'a> Lexer<'a> {
                         self.source.len()
                     };
 
+                    let literal = &self.source[start..end];
+                    if literal == "-" || literal == "+" {
+                        // the word after the sign, `-x` stops the number at `x`
+                        let word_len = self.source[end..]
+                            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                            .unwrap_or(self.source.len() - end);
+                        if self.options.allow_non_finite
+                            && &self.source[end..end + word_len] == "Infinity"
+                        {
+                            // ascii, so one char per byte
+                            (0..word_len).for_each(|_| {
+                                source_iter.next();
+                            });
+                            let infinity = if literal == "-" {
+                                f64::NEG_INFINITY
+                            } else {
+                                f64::INFINITY
+                            };
+                            self.tokens.push(Token::Number(infinity, self.line));
+                            continue;
+                        }
+                        return Err(LexError::DanglingSign(
+                            format!(
+                                "Sign '{}' not followed by a number at line: {}",
+                                literal, self.line
+                            ),
+                            start,
+                        ));
+                    }
+
                     match self.source[start..end].parse::<f64>() {
-                        Ok(f) => self.tokens.push(Token::Number(f, self.line)),
+                        Ok(f) => {
+                            let literal = &self.source[start..end];
+                            if self.options.warn_precision_loss && loses_precision(literal, f) {
+                                self.warnings.push(Warning::PrecisionLoss {
+                                    line: self.line,
+                                    literal: literal.to_string(),
+                                });
+                            }
+                            self.tokens.push(Token::Number(f, self.line));
+                        }
                         Err(_) => {
-                            return Err(LexError::InvalidNumber(format!(
-                                "Invalid number {} at line: {}",
-                                &self.source[start..end],
-                                self.line
-                            )))
+                            let literal = &self.source[start..end];
+                            let offset = invalid_number_offset(literal);
+                            let found = match literal[offset..].chars().next() {
+                                Some(c) => format!("'{}'", c),
+                                None => "end".to_string(),
+                            };
+                            return Err(LexError::InvalidNumber(
+                                format!(
+                                    "Invalid number {} at line: {}, unexpected {} at offset {}",
+                                    literal, self.line, found, offset
+                                ),
+                                start + offset,
+                            ));
                         }
                     }
                 }
                 invalid => {
-                    return Err(LexError::UnknownSymbol(format!(
-                        "Unknown symbol {} at line: {}",
-                        invalid, self.line
-                    )))
+                    // escaped so that control characters stay readable in the message
+                    return Err(LexError::UnknownSymbol(
+                        format!(
+                            "Unknown symbol {} at line: {}",
+                            invalid.escape_debug(),
+                            self.line
+                        ),
+                        start,
+                    ));
                 }
             }
         }
 
+        self.close_span(token_start, self.source.len());
         self.tokens.push(Token::EOF(self.line));
+        self.close_span(self.source.len(), self.source.len());
         Ok(()) // Lexing successful
     }
+
+    fn close_span(&mut self, start: usize, end: usize) {
+        if self.spans.len() < self.tokens.len() {
+            self.spans.push(Span { start, end });
+        }
+    }
 }
 
 #[test]
@@ -192,7 +639,7 @@ fn test_json_lexer() {
 }
 "#;
     let mut lexer = Lexer::new(source);
-    let _ = lexer.lex().unwrap();
+    lexer.lex().unwrap();
 
     let expected = vec![
         Token::LeftBrace(2),
@@ -269,3 +716,320 @@ fn test_escape_sequences() {
 
     assert_eq!(lexer.tokens, expected);
 }
+
+#[test]
+fn test_comments() {
+    let source = "// leading\n{\"a\": /* inline */ 1} /* multi\nline */\n";
+    let mut lexer = Lexer::with_options(
+        source,
+        LexerOptions {
+            allow_comments: true,
+            ..Default::default()
+        },
+    );
+    lexer.lex().unwrap();
+    let expected = vec![
+        Token::LeftBrace(2),
+        Token::Str("a".to_string(), 2),
+        Token::Colon(2),
+        Token::Number(1.0, 2),
+        Token::RightBrace(2),
+        Token::EOF(4),
+    ];
+    assert_eq!(lexer.tokens, expected);
+
+    let mut lexer = Lexer::new("/* note */ 42");
+    assert!(matches!(lexer.lex(), Err(LexError::UnknownSymbol(..))));
+
+    let options = LexerOptions {
+        allow_comments: true,
+        ..Default::default()
+    };
+    let mut lexer = Lexer::with_options("42 /* unterminated", options);
+    assert!(matches!(
+        lexer.lex(),
+        Err(LexError::UnterminatedComment(..))
+    ));
+}
+
+#[test]
+fn test_unknown_symbol_is_escaped() {
+    let mut lexer = Lexer::new("[1,\n\u{0}]");
+    let error = lexer.lex().unwrap_err();
+    assert_eq!(error.to_string(), r"Unknown symbol \0 at line: 2");
+
+    let mut lexer = Lexer::new("\u{7}");
+    let error = lexer.lex().unwrap_err();
+    assert_eq!(error.to_string(), r"Unknown symbol \u{7} at line: 1");
+
+    let mut lexer = Lexer::new("@");
+    let error = lexer.lex().unwrap_err();
+    assert_eq!(error.to_string(), "Unknown symbol @ at line: 1");
+}
+
+#[test]
+fn test_preserve_trivia() {
+    let source = "{\n  \"a\": 1, // one\n\t\"b\": /* two */ 2\n}";
+    let options = LexerOptions {
+        allow_comments: true,
+        preserve_trivia: true,
+        ..Default::default()
+    };
+    let mut lexer = Lexer::with_options(source, options);
+    lexer.lex().unwrap();
+    let expected = vec![
+        Token::LeftBrace(1),
+        Token::Whitespace("\n  ".to_string(), 1),
+        Token::Str("a".to_string(), 2),
+        Token::Colon(2),
+        Token::Whitespace(" ".to_string(), 2),
+        Token::Number(1.0, 2),
+        Token::Comma(2),
+        Token::Whitespace(" ".to_string(), 2),
+        Token::Comment("// one".to_string(), 2),
+        Token::Whitespace("\n\t".to_string(), 2),
+        Token::Str("b".to_string(), 3),
+        Token::Colon(3),
+        Token::Whitespace(" ".to_string(), 3),
+        Token::Comment("/* two */".to_string(), 3),
+        Token::Whitespace(" ".to_string(), 3),
+        Token::Number(2.0, 3),
+        Token::Whitespace("\n".to_string(), 3),
+        Token::RightBrace(4),
+        Token::EOF(4),
+    ];
+    assert_eq!(lexer.tokens, expected);
+}
+
+#[test]
+fn test_invalid_number_offset() {
+    let mut lexer = Lexer::new("[1.2.3]");
+    let error = lexer.lex().unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Invalid number 1.2.3 at line: 1, unexpected '.' at offset 3"
+    );
+
+    assert_eq!(invalid_number_offset("1e5e"), 3);
+    assert_eq!(invalid_number_offset("--1"), 1);
+    assert_eq!(invalid_number_offset("1e"), 2);
+    assert_eq!(invalid_number_offset("-"), 1);
+}
+
+#[test]
+fn test_pass_through_unknown_escapes() {
+    let source = r#"["a\b", "C:\Users\Documents", "\"\/\\\n\f"]"#;
+    let options = LexerOptions {
+        pass_through_unknown_escapes: true,
+        ..Default::default()
+    };
+    let mut lexer = Lexer::with_options(source, options);
+    lexer.lex().unwrap();
+    // `\b` is passed through on purpose, see the option
+    assert_eq!(lexer.tokens[1], Token::Str(r"a\b".to_string(), 1));
+    assert_eq!(
+        lexer.tokens[3],
+        Token::Str(r"C:\Users\Documents".to_string(), 1)
+    );
+    assert_eq!(lexer.tokens[5], Token::Str("\"/\\\n\u{c}".to_string(), 1));
+
+    let mut lexer = Lexer::new(source);
+    lexer.lex().unwrap();
+    assert_eq!(lexer.tokens[1], Token::Str("a\u{8}".to_string(), 1));
+    assert_eq!(lexer.tokens[5], Token::Str("\"/\\\n\u{c}".to_string(), 1));
+}
+
+#[test]
+fn test_spans() {
+    let source = "{\"key\": [1.5, true]}\n";
+    let mut lexer = Lexer::new(source);
+    lexer.lex().unwrap();
+    assert_eq!(lexer.spans.len(), lexer.tokens.len());
+    let texts: Vec<&str> = lexer
+        .spans
+        .iter()
+        .map(|span| &source[span.start..span.end])
+        .collect();
+    assert_eq!(
+        texts,
+        ["{", "\"key\"", ":", "[", "1.5", ",", "true", "]", "}", ""]
+    );
+}
+
+#[test]
+fn test_precision_loss() {
+    let source = "[9007199254740992, 9007199254740993,\n -12345678901234567890, 1.5, 1e300, 0]";
+    let options = LexerOptions {
+        warn_precision_loss: true,
+        ..Default::default()
+    };
+    let mut lexer = Lexer::with_options(source, options);
+    lexer.lex().unwrap();
+    assert_eq!(
+        lexer.warnings,
+        vec![
+            Warning::PrecisionLoss {
+                line: 1,
+                literal: "9007199254740993".to_string()
+            },
+            Warning::PrecisionLoss {
+                line: 2,
+                literal: "-12345678901234567890".to_string()
+            },
+        ]
+    );
+
+    // off by default
+    let mut lexer = Lexer::new(source);
+    lexer.lex().unwrap();
+    assert!(lexer.warnings.is_empty());
+}
+
+#[test]
+fn test_unicode_escapes() {
+    let mut lexer = Lexer::new(r#"["\u0041\u00e9", "\ud83d\ude00!", "\u006B"]"#);
+    lexer.lex().unwrap();
+    assert_eq!(lexer.tokens[1], Token::Str("Aé".to_string(), 1));
+    assert_eq!(lexer.tokens[3], Token::Str("😀!".to_string(), 1));
+    assert_eq!(lexer.tokens[5], Token::Str("k".to_string(), 1));
+
+    for source in [
+        r#""\u00""#,
+        r#""\u00zz""#,
+        r#""\ud83d""#,
+        r#""\ud83d\u0041""#,
+        r#""\ude00""#,
+    ] {
+        let mut lexer = Lexer::new(source);
+        assert!(
+            matches!(lexer.lex(), Err(LexError::InvalidEscape(..))),
+            "{} should be invalid",
+            source
+        );
+    }
+}
+
+#[test]
+fn test_new_at() {
+    let mut lexer = Lexer::new_at("{\n  \"a\": 1\n}", 42);
+    lexer.lex().unwrap();
+    assert_eq!(lexer.tokens[0], Token::LeftBrace(42));
+    assert_eq!(lexer.tokens[1], Token::Str("a".to_string(), 43));
+    assert_eq!(lexer.tokens[4], Token::RightBrace(44));
+
+    let mut lexer = Lexer::new_at("[\n\"open", 42);
+    assert_eq!(
+        lexer.lex().unwrap_err().to_string(),
+        "Unterminated string at line: 43"
+    );
+}
+
+#[test]
+fn test_reset() {
+    let documents = [r#"{"a": [1, 2, 3]}"#, "\n[true]", "\"x"];
+    let mut lexer = Lexer::new(documents[0]);
+    lexer.lex().unwrap();
+    let capacity = lexer.tokens.capacity();
+
+    lexer.reset(documents[1]);
+    lexer.lex().unwrap();
+    let mut fresh = Lexer::new(documents[1]);
+    fresh.lex().unwrap();
+    assert_eq!(lexer.tokens, fresh.tokens);
+    assert_eq!(lexer.spans, fresh.spans);
+    assert_eq!(lexer.tokens.capacity(), capacity);
+
+    lexer.reset(documents[2]);
+    assert!(matches!(lexer.lex(), Err(LexError::UnterminatedString(..))));
+    lexer.reset(documents[0]);
+    lexer.lex().unwrap();
+    assert_eq!(lexer.tokens.len(), 12);
+
+    let mut lexer = Lexer::new_at(documents[1], 42);
+    lexer.lex().unwrap();
+    lexer.reset(documents[1]);
+    lexer.lex().unwrap();
+    assert_eq!(lexer.tokens[0], Token::LeftBracket(43));
+}
+
+#[test]
+fn test_dangling_sign() {
+    for source in ["-", "[1, -x]", "+ 1", "[-\n1]"] {
+        let mut lexer = Lexer::new(source);
+        let error = lexer.lex().unwrap_err();
+        assert!(matches!(error, LexError::DanglingSign(..)), "{}", source);
+        assert!(
+            error
+                .to_string()
+                .ends_with("not followed by a number at line: 1")
+        );
+    }
+    let mut lexer = Lexer::new("-Infinity");
+    assert!(matches!(lexer.lex(), Err(LexError::DanglingSign(_, 0))));
+
+    let options = LexerOptions {
+        allow_non_finite: true,
+        ..Default::default()
+    };
+    let mut lexer =
+        Lexer::with_options("[-Infinity, Infinity, +Infinity, NaN, -Infinityx]", options);
+    assert!(matches!(lexer.lex(), Err(LexError::DanglingSign(_, 38))));
+    let mut lexer = Lexer::with_options("[-Infinity, Infinity, +Infinity, NaN]", options);
+    lexer.lex().unwrap();
+    assert_eq!(lexer.tokens[1], Token::Number(f64::NEG_INFINITY, 1));
+    assert_eq!(lexer.tokens[3], Token::Number(f64::INFINITY, 1));
+    assert_eq!(lexer.tokens[5], Token::Number(f64::INFINITY, 1));
+    assert!(matches!(lexer.tokens[7], Token::Number(n, 1) if n.is_nan()));
+    assert_eq!(
+        &lexer.source[lexer.spans[1].start..lexer.spans[1].end],
+        "-Infinity"
+    );
+}
+
+#[test]
+fn test_literal_case_hint() {
+    let mut lexer = Lexer::new("[True]");
+    let error = lexer.lex().unwrap_err();
+    assert!(matches!(error, LexError::UnknownLiteral(_, 1)));
+    assert_eq!(
+        error.to_string(),
+        "Unknown literal 'True' at line: 1, did you mean 'true'?"
+    );
+
+    let mut lexer = Lexer::new("NULL");
+    assert!(
+        lexer
+            .lex()
+            .unwrap_err()
+            .to_string()
+            .ends_with("did you mean 'null'?")
+    );
+
+    let mut lexer = Lexer::new("nil");
+    assert_eq!(
+        lexer.lex().unwrap_err().to_string(),
+        "Unknown literal 'nil' at line: 1"
+    );
+}
+
+#[test]
+fn test_undefined_as_null() {
+    let source = r#"{"a": undefined}"#;
+    let mut lexer = Lexer::new(source);
+    assert!(matches!(lexer.lex(), Err(LexError::UnknownLiteral(_, 6))));
+
+    let options = LexerOptions {
+        undefined_as_null: true,
+        ..Default::default()
+    };
+    let mut lexer = Lexer::with_options(source, options);
+    lexer.lex().unwrap();
+    let value = crate::Parser::new(lexer.tokens)
+        .parse()
+        .unwrap()
+        .into_owned();
+    assert_eq!(value, crate::parse_str(r#"{"a": null}"#).unwrap());
+
+    let mut lexer = Lexer::with_options("undefinedx", options);
+    assert!(matches!(lexer.lex(), Err(LexError::UnknownLiteral(..))));
+}
@@ -1,2 +1,338 @@
+// without the `std` feature only `alloc` is needed, the test harness always
+// needs `std` though
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+use alloc::vec::Vec;
+
+extern crate alloc;
+
+#[cfg(feature = "base64")]
+pub mod base64;
+pub mod convert;
+#[cfg(feature = "encoding")]
+pub mod encoding;
+pub mod error;
 pub mod lexer;
+pub mod map;
+#[cfg(all(feature = "mmap", unix))]
+pub mod mmap;
 pub mod parser;
+pub mod patch;
+pub mod path;
+pub mod pointer;
+pub mod serializer;
+mod siphash;
+pub mod spanned;
+pub mod value;
+pub mod visitor;
+
+pub use convert::{FromValue, FromValueError, to_csv};
+pub use error::JsonError;
+pub use lexer::{LexError, Lexer, LexerOptions, Span, Token, Warning};
+pub use map::Map;
+pub use parser::{OwnedValue, ParseError, Parser, ParserOptions, Value, pretty_print};
+pub use patch::{PatchError, apply_merge_patch, diff_as_patch};
+pub use path::PathError;
+pub use pointer::PointerError;
+pub use serializer::{
+    IndentStyle, KeyOrder, SerializeOptions, serialize_pointer, to_string, to_string_pretty,
+    to_string_with,
+};
+pub use spanned::{SpannedEntry, SpannedKind, SpannedValue, parse_spanned};
+pub use value::{KeyConflict, Rng, TypeSet, TypeTag};
+pub use visitor::{FieldVisitor, Scalar, deserialize_struct};
+
+/// Lexes and parses `input` in one go. The returned tree owns its strings since
+/// the tokens are dropped before returning
+pub fn parse_str(input: &str) -> Result<OwnedValue, JsonError> {
+    let mut lexer = Lexer::new(input);
+    lexer.lex()?;
+    let parser = Parser::from_lexer(lexer, ParserOptions::default());
+    Ok(parser.parse()?.into_owned())
+}
+
+/// Same as [`parse_str`] but fails with [`ParseError::WrongTopLevel`] unless the
+/// document is an object, like many APIs require
+pub fn parse_object(input: &str) -> Result<OwnedValue, JsonError> {
+    parse_rooted(input, TypeTag::Object)
+}
+
+/// Same as [`parse_str`] but fails with [`ParseError::WrongTopLevel`] unless the
+/// document is an array
+pub fn parse_array(input: &str) -> Result<OwnedValue, JsonError> {
+    parse_rooted(input, TypeTag::Array)
+}
+
+/// Parses `input` and checks that the result is of type `expected`
+fn parse_rooted(input: &str, expected: TypeTag) -> Result<OwnedValue, JsonError> {
+    let mut lexer = Lexer::new(input);
+    lexer.lex()?;
+    let parser = Parser::from_lexer(lexer, ParserOptions::default());
+    let value = parser.parse()?;
+    if !value.is_type(expected) {
+        return Err(ParseError::WrongTopLevel {
+            expected,
+            found: TypeTag::of(&value),
+            offset: parser.spans().first().map(|span| span.start),
+        }
+        .into());
+    }
+    Ok(value.into_owned())
+}
+
+/// Parses every non-empty line of `input` as a separate document, like JSON
+/// Lines (NDJSON). Either every line parses, or the errors of all failing lines
+/// are returned with their line numbers, which the error messages use too
+pub fn from_lines(input: &str) -> Result<Vec<OwnedValue>, Vec<(usize, JsonError)>> {
+    let mut values = Vec::new();
+    let mut errors = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_line(line, i + 1) {
+            Ok(value) => values.push(value),
+            Err(error) => errors.push((i + 1, error)),
+        }
+    }
+    if errors.is_empty() {
+        Ok(values)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Parses the value at the start of `input` and returns it along with the number
+/// of bytes it took, so that a reader of delimited frames (like JSON-RPC over a
+/// stream) knows where the next one starts. Leading whitespace is part of the
+/// count, but whitespace after the value is not: the count ends right after the
+/// value's last byte, so `input[count..]` starts with the separator. What
+/// follows the value isn't parsed, although it must still lex
+pub fn parse_prefix(input: &str) -> Result<(OwnedValue, usize), JsonError> {
+    let mut lexer = Lexer::new(input);
+    lexer.lex_prefix()?;
+    let parser = Parser::from_lexer(lexer, ParserOptions::default());
+    let (value, consumed) = parser.parse_prefix()?;
+    Ok((value.into_owned(), consumed))
+}
+
+/// Converts JSON Lines (NDJSON) into a single array with one element per
+/// non-empty line, stopping at the first line that doesn't parse. Its line
+/// number is in the error message
+pub fn ndjson_to_array(input: &str) -> Result<OwnedValue, JsonError> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| parse_line(line, i + 1))
+        .collect()
+}
+
+/// Parses `line` as a whole document, numbering its lines from `number`
+fn parse_line(line: &str, number: usize) -> Result<OwnedValue, JsonError> {
+    let mut lexer = Lexer::new_at(line, number);
+    lexer.lex()?;
+    let parser = Parser::from_lexer(lexer, ParserOptions::default());
+    Ok(parser.parse()?.into_owned())
+}
+
+/// Same as [`parse_str`] but also returns a [`Warning`] for every integer that
+/// was rounded when stored as `f64`
+pub fn parse_str_with_warnings(input: &str) -> Result<(OwnedValue, Vec<Warning>), JsonError> {
+    let options = LexerOptions {
+        warn_precision_loss: true,
+        ..Default::default()
+    };
+    let mut lexer = Lexer::with_options(input, options);
+    lexer.lex()?;
+    let warnings = core::mem::take(&mut lexer.warnings);
+    let parser = Parser::from_lexer(lexer, ParserOptions::default());
+    Ok((parser.parse()?.into_owned(), warnings))
+}
+
+/// Parses `input`, which must be an array, and calls `f` with one element at a
+/// time instead of building the whole array. The elements own their strings
+pub fn for_each_array_element(input: &str, mut f: impl FnMut(OwnedValue)) -> Result<(), JsonError> {
+    let mut lexer = Lexer::new(input);
+    lexer.lex()?;
+    let parser = Parser::from_lexer(lexer, ParserOptions::default());
+    parser.for_each_array_element(|value| f(value.into_owned()))?;
+    Ok(())
+}
+
+/// Checks that `input` is exactly one valid json value without building the tree
+pub fn validate_syntax(input: &str) -> Result<(), JsonError> {
+    let mut lexer = Lexer::new(input);
+    lexer.lex()?;
+    Parser::from_lexer(lexer, ParserOptions::default()).validate()?;
+    Ok(())
+}
+
+/// Same as [`validate_syntax`] when only a yes/no answer is needed
+pub fn is_valid(input: &str) -> bool {
+    validate_syntax(input).is_ok()
+}
+
+#[test]
+fn test_is_valid() {
+    assert!(is_valid(value::NESTED_STRUCTURES));
+    assert!(is_valid("[]"));
+    assert!(is_valid("{\"a\": {}}"));
+    assert!(is_valid(" 42 "));
+
+    for input in [
+        "",
+        "[1, 2",
+        "[1, 2,]",
+        "{\"a\" 1}",
+        "{1: 2}",
+        "{\"a\": 1,}",
+        "[1 2]",
+        "true false",
+        "\"unterminated",
+        "nul",
+    ] {
+        assert!(!is_valid(input), "{:?} should be invalid", input);
+    }
+    assert!(matches!(
+        validate_syntax("[1, 2,]"),
+        Err(JsonError::Parse(ParseError::TrailingComma { line: 1, .. }))
+    ));
+    assert!(matches!(validate_syntax("nul"), Err(JsonError::Lex(_))));
+}
+
+#[test]
+fn test_parse_str_with_warnings() {
+    let (value, warnings) = parse_str_with_warnings(r#"{"id": 9007199254740993}"#).unwrap();
+    assert_eq!(value.to_string(), r#"{"id":9007199254740992}"#);
+    assert_eq!(
+        warnings,
+        vec![Warning::PrecisionLoss {
+            line: 1,
+            literal: "9007199254740993".to_string()
+        }]
+    );
+    let (_, warnings) = parse_str_with_warnings(value::NESTED_STRUCTURES).unwrap();
+    assert!(warnings.is_empty());
+}
+
+#[test]
+fn test_for_each_array_element() {
+    let input = format!(
+        "[{}]",
+        (0..1000)
+            .map(|i| format!(r#"{{"id": {}, "amount": {}}}"#, i, i % 7))
+            .collect::<Vec<_>>()
+            .join(",")
+    );
+    let mut total = 0.0;
+    let mut count = 0;
+    for_each_array_element(&input, |element| {
+        total += f64::from_value(element.pointer("/amount").unwrap()).unwrap();
+        count += 1;
+    })
+    .unwrap();
+    assert_eq!(count, 1000);
+    assert_eq!(total, (0..1000).map(|i| (i % 7) as f64).sum::<f64>());
+
+    for_each_array_element("[]", |_| unreachable!()).unwrap();
+    assert!(matches!(
+        for_each_array_element(r#"{"a": 1}"#, |_| {}),
+        Err(JsonError::Parse(ParseError::UnexpectedToken(..)))
+    ));
+    assert!(for_each_array_element("[1, 2,]", |_| {}).is_err());
+}
+
+#[test]
+fn test_from_lines() {
+    let input = "{\"id\": 1}\n[2, 3]\n\n\"four\"\n";
+    let values = from_lines(input).unwrap();
+    assert_eq!(values.len(), 3);
+    assert_eq!(values[1], parse_str("[2, 3]").unwrap());
+
+    let input = "{\"id\": 1}\n{\"id\": }\n  \n[1, 2\ntrue";
+    let errors = from_lines(input).unwrap_err();
+    let lines: Vec<usize> = errors.iter().map(|(line, _)| *line).collect();
+    assert_eq!(lines, [2, 4]);
+    assert_eq!(errors[0].1.to_string(), "Unexpected token '}' at line: 2");
+}
+
+#[test]
+fn test_parse_prefix() {
+    let input = "{\"a\":1}\n{\"b\":2}";
+    let (value, consumed) = parse_prefix(input).unwrap();
+    assert_eq!(value, parse_str("{\"a\":1}").unwrap());
+    assert_eq!(consumed, 7);
+    assert_eq!(&input[consumed..], "\n{\"b\":2}");
+
+    let (value, consumed) = parse_prefix(&input[consumed..]).unwrap();
+    assert_eq!(value, parse_str("{\"b\":2}").unwrap());
+    assert_eq!(consumed, 8);
+
+    assert_eq!(parse_prefix("  \"é\"  ").unwrap().1, 6);
+    assert_eq!(parse_prefix("12 true").unwrap().1, 2);
+    // only the first value is lexed, a truncated frame after it is fine
+    let (value, consumed) = parse_prefix("{\"a\":1}\n{\"b\":\"unterm").unwrap();
+    assert_eq!(value, parse_str("{\"a\":1}").unwrap());
+    assert_eq!(consumed, 7);
+    assert_eq!(parse_prefix("[[1], 2] x").unwrap().1, 8);
+
+    assert!(parse_prefix("[1, 2").is_err());
+    assert!(parse_prefix("   ").is_err());
+}
+
+#[test]
+fn test_ndjson_to_array() {
+    let input = "{\"id\": 1}\n\n[true]\n\"three\"\n";
+    assert_eq!(
+        ndjson_to_array(input).unwrap(),
+        parse_str(r#"[{"id": 1}, [true], "three"]"#).unwrap()
+    );
+    assert_eq!(ndjson_to_array("").unwrap(), Value::new_array());
+
+    let error = ndjson_to_array("1\n[2,]\n{").unwrap_err();
+    assert!(error.to_string().contains("line: 2"), "{}", error);
+}
+
+/// Runs with `cargo test --no-default-features`, which leaves out everything
+/// behind the `std` feature. The test harness still links `std`, so this doesn't
+/// show that the crate builds without it, CI builds it for a target without `std`
+/// for that
+#[cfg(not(feature = "std"))]
+#[test]
+fn test_no_std() {
+    let value = parse_str(r#"{"a": [1, "b", null]}"#).unwrap();
+    assert_eq!(to_string(&value), r#"{"a":[1,"b",null]}"#);
+    assert_eq!(value.pointer("/a/1"), Some(&Value::Str("b".into())));
+    assert!(matches!(parse_str("[1,"), Err(JsonError::Parse(_))));
+}
+
+#[test]
+fn test_parse_object() {
+    assert_eq!(parse_object("{}").unwrap(), Value::new_object());
+    assert!(matches!(
+        parse_object(" 5"),
+        Err(JsonError::Parse(ParseError::WrongTopLevel {
+            expected: TypeTag::Object,
+            found: TypeTag::Number,
+            offset: Some(1)
+        }))
+    ));
+    assert_eq!(
+        parse_object("[]").unwrap_err().to_string(),
+        "Expected the document to be an object, got array"
+    );
+    // syntax errors come first
+    assert!(matches!(
+        parse_object("5 6"),
+        Err(JsonError::Parse(ParseError::TrailingData { .. }))
+    ));
+
+    assert_eq!(parse_array("[1]").unwrap(), parse_str("[1]").unwrap());
+    assert!(matches!(
+        parse_array("{}"),
+        Err(JsonError::Parse(ParseError::WrongTopLevel {
+            found: TypeTag::Object,
+            ..
+        }))
+    ));
+}
@@ -0,0 +1,338 @@
+use crate::parser::Value;
+use alloc::borrow::Cow;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+
+// without `std` there is no `HashMap`, lookups go through a `BTreeMap` instead
+#[cfg(feature = "std")]
+type Index<'a> = std::collections::HashMap<Cow<'a, str>, usize>;
+#[cfg(not(feature = "std"))]
+type Index<'a> = alloc::collections::BTreeMap<Cow<'a, str>, usize>;
+
+/// Storage for the pairs of a json object that remembers the order in which the
+/// keys were inserted. Lookups go through a `HashMap` from key to position in
+/// `entries`, so they don't depend on the number of keys (a `BTreeMap` without
+/// the `std` feature, so they take logarithmic time)
+///
+/// Equality ignores the order, two maps are equal when they have the same keys
+/// mapped to equal values
+#[derive(Debug, Clone, Default)]
+pub struct Map<'a> {
+    entries: Vec<(Cow<'a, str>, Value<'a>)>,
+    index: Index<'a>,
+}
+
+impl<'a> Map<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: Vec::with_capacity(capacity),
+            #[cfg(feature = "std")]
+            index: Index::with_capacity(capacity),
+            #[cfg(not(feature = "std"))]
+            index: Index::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value<'a>> {
+        self.index.get(key).map(|&i| &self.entries[i].1)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value<'a>> {
+        self.index.get(key).map(|&i| &mut self.entries[i].1)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// Inserts a pair at the end, unless the key is already present in which case
+    /// only its value is replaced and the old value is returned
+    pub fn insert(&mut self, key: Cow<'a, str>, value: Value<'a>) -> Option<Value<'a>> {
+        if let Some(&i) = self.index.get(&key) {
+            return Some(core::mem::replace(&mut self.entries[i].1, value));
+        }
+        self.index.insert(key.clone(), self.entries.len());
+        self.entries.push((key, value));
+        None
+    }
+
+    /// Returns the value for `key`, inserting the result of `f` at the end first if
+    /// the key is missing
+    pub fn get_mut_or_insert_with(
+        &mut self,
+        key: &str,
+        f: impl FnOnce() -> Value<'a>,
+    ) -> &mut Value<'a> {
+        let i = match self.index.get(key) {
+            Some(&i) => i,
+            None => {
+                self.insert(Cow::Owned(key.to_string()), f());
+                self.entries.len() - 1
+            }
+        };
+        &mut self.entries[i].1
+    }
+
+    /// Pushes a pair at the end even if the key is already present, which turns
+    /// the map into a multimap. Lookups then return the value pushed last, while
+    /// iteration and [`Map::as_slice`] still see every pair
+    pub fn append(&mut self, key: Cow<'a, str>, value: Value<'a>) {
+        self.index.insert(key.clone(), self.entries.len());
+        self.entries.push((key, value));
+    }
+
+    /// Builds a map from pairs with [`Map::append`], so duplicate keys are kept.
+    /// `collect` replaces them instead, like `HashMap`
+    pub fn from_entries(entries: impl IntoIterator<Item = (Cow<'a, str>, Value<'a>)>) -> Self {
+        let mut map = Map::new();
+        for (key, value) in entries {
+            map.append(key, value);
+        }
+        map
+    }
+
+    /// Removes the pair with `key`, shifting the pairs after it to keep the order.
+    /// With duplicate keys every pair with `key` is removed and the value pushed
+    /// last is returned
+    pub fn remove(&mut self, key: &str) -> Option<Value<'a>> {
+        let position = self.index.remove(key)?;
+        let (_, value) = self.entries.remove(position);
+        if self.entries.iter().any(|(k, _)| k == key) {
+            self.entries.retain(|(k, _)| k != key);
+            self.reindex();
+        } else {
+            for i in self.index.values_mut() {
+                if *i > position {
+                    *i -= 1;
+                }
+            }
+        }
+        Some(value)
+    }
+
+    /// Renames the pair with key `from` to `to`, keeping its position. A pair
+    /// already using `to` is removed first. Returns whether `from` was present
+    pub fn rename(&mut self, from: &str, to: Cow<'a, str>) -> bool {
+        if !self.contains_key(from) {
+            return false;
+        }
+        if from == to {
+            return true;
+        }
+        self.entries.retain(|(key, _)| *key != to);
+        for (key, _) in &mut self.entries {
+            if key == from {
+                *key = to.clone();
+            }
+        }
+        self.reindex();
+        true
+    }
+
+    /// Reorders the pairs by key, pairs with equal keys keep their order
+    pub fn sort_keys(&mut self) {
+        self.entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        self.reindex();
+    }
+
+    fn reindex(&mut self) {
+        self.index = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(i, (key, _))| (key.clone(), i))
+            .collect();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Cow<'a, str>, &Value<'a>)> {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&Cow<'a, str>, &mut Value<'a>)> {
+        self.entries.iter_mut().map(|(key, value)| (&*key, value))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Cow<'a, str>> {
+        self.entries.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Value<'a>> {
+        self.entries.iter().map(|(_, value)| value)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut Value<'a>> {
+        self.entries.iter_mut().map(|(_, value)| value)
+    }
+
+    /// The pairs in insertion order
+    pub fn as_slice(&self) -> &[(Cow<'a, str>, Value<'a>)] {
+        &self.entries
+    }
+}
+
+impl PartialEq for Map<'_> {
+    /// Order doesn't matter. Maps holding duplicate keys from [`Map::append`]
+    /// compare their pairs as multisets, so every pair has to appear equally
+    /// often on both sides
+    fn eq(&self, other: &Self) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+        if self.index.len() == self.len() && other.index.len() == other.len() {
+            return self
+                .iter()
+                .all(|(key, value)| other.get(key) == Some(value));
+        }
+        let mut used = vec![false; other.len()];
+        self.entries.iter().all(|pair| {
+            let found = other
+                .entries
+                .iter()
+                .enumerate()
+                .position(|(i, candidate)| !used[i] && candidate == pair);
+            found.map(|i| used[i] = true).is_some()
+        })
+    }
+}
+
+impl<'a> core::ops::Index<&str> for Map<'a> {
+    type Output = Value<'a>;
+
+    /// Panics if `key` isn't present, like `HashMap`
+    fn index(&self, key: &str) -> &Value<'a> {
+        self.get(key).expect("key not found in map")
+    }
+}
+
+impl<'a> FromIterator<(Cow<'a, str>, Value<'a>)> for Map<'a> {
+    fn from_iter<I: IntoIterator<Item = (Cow<'a, str>, Value<'a>)>>(iter: I) -> Self {
+        let mut map = Map::new();
+        for (key, value) in iter {
+            map.insert(key, value);
+        }
+        map
+    }
+}
+
+impl<'a> IntoIterator for Map<'a> {
+    type Item = (Cow<'a, str>, Value<'a>);
+    type IntoIter = alloc::vec::IntoIter<(Cow<'a, str>, Value<'a>)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl<'m, 'a> IntoIterator for &'m Map<'a> {
+    type Item = (&'m Cow<'a, str>, &'m Value<'a>);
+    type IntoIter = core::iter::Map<
+        core::slice::Iter<'m, (Cow<'a, str>, Value<'a>)>,
+        fn(&'m (Cow<'a, str>, Value<'a>)) -> (&'m Cow<'a, str>, &'m Value<'a>),
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+}
+
+#[test]
+fn test_insertion_order() {
+    let mut map = Map::new();
+    map.insert("b".into(), Value::Number(1.0));
+    map.insert("a".into(), Value::Number(2.0));
+    map.insert("c".into(), Value::Number(3.0));
+    assert_eq!(
+        map.insert("a".into(), Value::Null),
+        Some(Value::Number(2.0))
+    );
+    assert_eq!(map.keys().collect::<Vec<_>>(), ["b", "a", "c"]);
+
+    assert_eq!(map.remove("b"), Some(Value::Number(1.0)));
+    assert_eq!(map.remove("b"), None);
+    assert_eq!(map.keys().collect::<Vec<_>>(), ["a", "c"]);
+    assert_eq!(map["c"], Value::Number(3.0));
+    assert_eq!(map.get("a"), Some(&Value::Null));
+}
+
+#[test]
+fn test_equality_ignores_order() {
+    let a: Map = [("x".into(), Value::Null), ("y".into(), Value::Bool(true))]
+        .into_iter()
+        .collect();
+    let b: Map = [("y".into(), Value::Bool(true)), ("x".into(), Value::Null)]
+        .into_iter()
+        .collect();
+    assert_eq!(a, b);
+
+    let c: Map = [("y".into(), Value::Bool(false)), ("x".into(), Value::Null)]
+        .into_iter()
+        .collect();
+    assert_ne!(a, c);
+}
+
+#[test]
+fn test_append_duplicates() {
+    let mut map = Map::new();
+    map.append("a".into(), Value::Number(1.0));
+    map.append("b".into(), Value::Number(2.0));
+    map.append("a".into(), Value::Number(3.0));
+    assert_eq!(map.len(), 3);
+    assert_eq!(map["a"], Value::Number(3.0));
+
+    assert_eq!(map.remove("a"), Some(Value::Number(3.0)));
+    assert_eq!(map.len(), 1);
+    assert_eq!(map["b"], Value::Number(2.0));
+}
+
+#[test]
+fn test_equality_with_duplicates() {
+    let a = Map::from_entries([
+        ("a".into(), Value::Number(2.0)),
+        ("a".into(), Value::Number(2.0)),
+    ]);
+    let b = Map::from_entries([
+        ("a".into(), Value::Number(1.0)),
+        ("a".into(), Value::Number(2.0)),
+    ]);
+    assert_ne!(a, b);
+    assert_ne!(b, a);
+
+    let c = Map::from_entries([
+        ("a".into(), Value::Number(2.0)),
+        ("a".into(), Value::Number(1.0)),
+    ]);
+    assert_eq!(b, c);
+    assert_eq!(c, b);
+}
+
+#[test]
+fn test_rename() {
+    let mut map: Map = [
+        ("a".into(), Value::Number(1.0)),
+        ("b".into(), Value::Number(2.0)),
+        ("c".into(), Value::Number(3.0)),
+    ]
+    .into_iter()
+    .collect();
+    assert!(map.rename("a", "z".into()));
+    assert!(!map.rename("a", "y".into()));
+    assert_eq!(map.keys().collect::<Vec<_>>(), ["z", "b", "c"]);
+
+    assert!(map.rename("c", "b".into()));
+    assert_eq!(map.keys().collect::<Vec<_>>(), ["z", "b"]);
+    assert_eq!(map["b"], Value::Number(3.0));
+    assert_eq!(map["z"], Value::Number(1.0));
+}
@@ -0,0 +1,103 @@
+use crate::error::JsonError;
+use crate::parser::OwnedValue;
+use std::ffi::{c_int, c_long, c_void};
+use std::fs::File;
+use std::os::fd::AsRawFd;
+use std::path::Path;
+
+// declared directly to avoid depending on the libc crate
+unsafe extern "C" {
+    fn mmap(
+        addr: *mut c_void,
+        len: usize,
+        prot: c_int,
+        flags: c_int,
+        fd: c_int,
+        offset: c_long,
+    ) -> *mut c_void;
+    fn munmap(addr: *mut c_void, len: usize) -> c_int;
+}
+
+// same values on linux and the BSDs (including macOS)
+const PROT_READ: c_int = 1;
+const MAP_PRIVATE: c_int = 2;
+const MAP_FAILED: *mut c_void = !0 as *mut c_void;
+
+/// A read-only private mapping of a whole file, unmapped on drop
+struct Mmap {
+    ptr: *mut c_void,
+    len: usize,
+}
+
+impl Mmap {
+    /// `len` must be non-zero, mapping an empty range fails
+    fn map(file: &File, len: usize) -> std::io::Result<Self> {
+        // SAFETY: a fresh mapping chosen by the kernel doesn't alias any memory
+        // owned by rust, and the arguments are checked by the kernel
+        let ptr = unsafe {
+            mmap(
+                std::ptr::null_mut(),
+                len,
+                PROT_READ,
+                MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == MAP_FAILED {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(Self { ptr, len })
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        // SAFETY: the mapping is `len` bytes long and readable until dropped
+        unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        // SAFETY: `ptr` and `len` describe a mapping created by `Mmap::map`
+        unsafe {
+            munmap(self.ptr, self.len);
+        }
+    }
+}
+
+/// Parses the file at `path` through a memory map, so the OS pages the contents
+/// in on demand instead of reading them into a buffer first. The contents must be
+/// valid UTF-8
+///
+/// # Safety
+///
+/// Nothing else may write to or truncate the file until this returns. Writes
+/// change the bytes under the parser while it borrows them, and touching a
+/// page past the end of a truncated file raises `SIGBUS`
+pub unsafe fn from_mmap<P: AsRef<Path>>(path: P) -> Result<OwnedValue, JsonError> {
+    let file = File::open(path)?;
+    let len = file.metadata()?.len() as usize;
+    if len == 0 {
+        return crate::parse_str("");
+    }
+    let map = Mmap::map(&file, len)?;
+    let source = std::str::from_utf8(map.as_bytes())?;
+    crate::parse_str(source)
+}
+
+#[test]
+fn test_from_mmap() {
+    let path = std::env::temp_dir().join(format!("json_parser_mmap_{}.json", std::process::id()));
+    std::fs::write(&path, crate::value::NESTED_STRUCTURES).unwrap();
+    // SAFETY: the file is private to this test and only rewritten in between
+    let value = unsafe { from_mmap(&path) };
+    std::fs::write(&path, b"[\"\xff\"]").unwrap();
+    let invalid = unsafe { from_mmap(&path) };
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        value.unwrap(),
+        crate::parse_str(crate::value::NESTED_STRUCTURES).unwrap()
+    );
+    assert!(matches!(invalid, Err(JsonError::Utf8(_))));
+}
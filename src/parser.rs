@@ -1,32 +1,63 @@
-use crate::lexer::Token;
-use std::cell::Cell;
-use std::collections::HashMap;
+use crate::lexer::{Lexer, Span, Token};
+use crate::map::Map;
+use crate::serializer::escape_string;
+use crate::value::TypeTag;
+use crate::visitor::{FieldVisitor, Scalar};
+use alloc::borrow::Cow;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cell::Cell;
 
 /// # Json Grammar
 /// - `value = dict | list | string | number | "true" | "false" | "null"`
 /// - `list = "[" [value ("," value)*] "]"`
 /// - `dict = "{" [pair ("," pair)*] "}"`
 /// - `pair = string ":" value`
-#[derive(Debug, PartialEq)]
+///
+/// Strings and keys are borrowed from the tokens where possible, see
+/// [`OwnedValue`] for a tree that owns all of its strings. Objects keep their keys
+/// in the order they appear in the source
+///
+/// # Equality
+/// There is no separate integer variant, every number is stored as `f64`, so
+/// `5`, `5.0` and `5e0` all parse to `Number(5.0)` and compare equal. Numbers are
+/// compared with `f64` equality after parsing, which means:
+/// - `0` and `-0` are equal, and `NaN` (which only comes from code, not from
+///   json text) is never equal to anything, itself included.
+/// - Integers beyond 2^53 are rounded to the nearest `f64` first, so two literals
+///   like `9007199254740992` and `9007199254740993` compare equal. Use
+///   [`crate::parse_str_with_warnings`] to detect this.
+/// - Objects compare equal regardless of the order of their keys.
+#[derive(Debug, PartialEq, Clone)]
 pub enum Value<'a> {
-    Dict(HashMap<&'a str, Value<'a>>),
+    Dict(Map<'a>),
     List(Vec<Value<'a>>),
     Bool(bool),
-    Str(&'a str),
+    Str(Cow<'a, str>),
     Number(f64),
     Null,
 }
 
+/// A `Value` that doesn't borrow from the tokens or the source, so it can outlive
+/// both. Use [`Value::into_owned`] to convert a borrowed tree
+pub type OwnedValue = Value<'static>;
+
 /// # Prints the s-expression representation of the json object
 /// Json Grammer is transformed according to the following rules
 /// - `dict = "(" [pair (" " pair)*] ")"`
 /// - `pair = "(" key " " value ")"`
 /// - `list = "(" [value (" "value)*] ")"`
 /// - The keys are printed without quotes.
-/// - The atoms `(string | number | "true" | "false" | "null")` are printed as is.
+/// - Empty dicts and lists are printed as `()`.
+/// - The atoms `(number | "true" | "false" | "null")` are printed as is.
+/// - Strings are printed in double quotes with `"`, `\` and control characters
+///   escaped.
 fn _pretty_print(val: &Value<'_>, indent: usize) -> String {
     let indent_str = "  ".repeat(indent);
     match val {
+        Value::Dict(map) if map.is_empty() => "()".to_string(),
+        Value::List(list) if list.is_empty() => "()".to_string(),
         Value::Dict(map) => {
             let mut result = String::from("(");
             for (key, value) in map {
@@ -52,7 +83,7 @@ fn _pretty_print(val: &Value<'_>, indent: usize) -> String {
             result.push_str(&format!("\n{})", indent_str));
             result
         }
-        Value::Str(s) => format!("\"{}\"", s),
+        Value::Str(s) => format!("\"{}\"", escape_string(s)),
         Value::Number(n) => n.to_string(),
         Value::Bool(b) => b.to_string(),
         Value::Null => "null".to_string(),
@@ -63,111 +94,571 @@ pub fn pretty_print(value: &Value) -> String {
     _pretty_print(value, 0)
 }
 
+/// The offsets are the byte offset of the offending token in the source, only
+/// known when the parser was created with [`Parser::from_lexer`]
 #[derive(Debug)]
 pub enum ParseError {
-    UnexpectedToken(String),
-    InvalidKey(String),
+    UnexpectedToken(String, Option<usize>), // message + offset
+    InvalidKey(String, Option<usize>),
+    /// A `,` directly followed by the closing `]` or `}`
+    TrailingComma {
+        line: usize,
+        offset: Option<usize>,
+    },
+    /// Another token after a complete top-level value
+    TrailingData {
+        line: usize,
+        offset: Option<usize>,
+    },
+    /// A `:` outside of an object, or after a value inside one
+    UnexpectedColon {
+        line: usize,
+        offset: Option<usize>,
+    },
+    /// A `,` where an element or a pair should be, like the doubled one in
+    /// `[1,,2]`
+    UnexpectedComma {
+        line: usize,
+        offset: Option<usize>,
+    },
+    /// The document is valid but its top-level value isn't of the type required
+    /// by [`crate::parse_object`] or [`crate::parse_array`]
+    WrongTopLevel {
+        expected: TypeTag,
+        found: TypeTag,
+        offset: Option<usize>,
+    },
+    /// An object with more keys than [`ParserOptions::max_object_keys`], the
+    /// position is the one of the first key over the limit
+    TooManyKeys {
+        limit: usize,
+        line: usize,
+        offset: Option<usize>,
+    },
+}
+
+impl ParseError {
+    /// Byte offset in the source of the token the error is about
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            ParseError::UnexpectedToken(_, offset)
+            | ParseError::InvalidKey(_, offset)
+            | ParseError::TrailingComma { offset, .. }
+            | ParseError::TrailingData { offset, .. }
+            | ParseError::UnexpectedColon { offset, .. }
+            | ParseError::UnexpectedComma { offset, .. }
+            | ParseError::WrongTopLevel { offset, .. }
+            | ParseError::TooManyKeys { offset, .. } => *offset,
+        }
+    }
+}
+
+impl core::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ParseError::UnexpectedToken(message, _) | ParseError::InvalidKey(message, _) => {
+                write!(f, "{}", message)
+            }
+            ParseError::TrailingComma { line, .. } => {
+                write!(
+                    f,
+                    "Trailing comma before the closing bracket at line: {}",
+                    line
+                )
+            }
+            ParseError::TrailingData { line, .. } => {
+                write!(f, "Unexpected data after the value at line: {}", line)
+            }
+            ParseError::UnexpectedColon { line, .. } => write!(
+                f,
+                "Unexpected ':' at line: {}, colons only separate keys from values in objects",
+                line
+            ),
+            ParseError::UnexpectedComma { line, .. } => write!(
+                f,
+                "Unexpected ',' at line: {}, an element is missing before it",
+                line
+            ),
+            ParseError::WrongTopLevel {
+                expected, found, ..
+            } => write!(
+                f,
+                "Expected the document to be an {}, got {}",
+                expected, found
+            ),
+            ParseError::TooManyKeys { limit, line, .. } => {
+                write!(f, "Object has more than {} keys at line: {}", limit, line)
+            }
+        }
+    }
+}
+
+/// Deviations from strict json while parsing, all of them are off by default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParserOptions {
+    /// When the tokens end while containers are still open, close them and return
+    /// what was parsed so far instead of failing. A dangling key or comma is
+    /// dropped. [`Parser::recovered`] tells whether this happened. Only meant for
+    /// salvaging truncated input, never for validation
+    pub recover_truncated: bool,
+    /// Keep every pair of an object even when keys repeat, instead of the last
+    /// value replacing the earlier ones. See [`Map::append`]
+    pub keep_duplicate_keys: bool,
+    /// Fail with [`ParseError::TooManyKeys`] as soon as a single object gets more
+    /// keys than this, before its map grows any further. Guards against
+    /// documents built to exhaust memory through one huge object
+    pub max_object_keys: Option<usize>,
+    /// Keep one copy of every object key: the parser frees the text of repeated
+    /// key tokens and every key borrows the slice of its first occurrence, so
+    /// the keys of 1000 same-shaped records take the memory of one. The sharing
+    /// is lost with [`Value::into_owned`], which copies every key. Costs a table
+    /// built before parsing, `benches/intern_keys.rs` measures both
+    pub intern_keys: bool,
 }
 
 pub struct Parser {
     tokens: Vec<Token>,
+    spans: Vec<Span>,     // empty unless created with `from_lexer`
     current: Cell<usize>, // to allow interior mutability
+    options: ParserOptions,
+    recovered: Cell<bool>,
+    // with `intern_keys`, the index of the first key token with the same text for
+    // every token, otherwise empty. Only that first token keeps the text
+    key_origins: Vec<usize>,
 }
 
 impl Parser {
-    // move the tokens emitted by the lexer
+    // move the tokens emitted by the lexer, whitespace and comment tokens are
+    // dropped since they don't affect the value
     pub fn new(tokens: Vec<Token>) -> Self {
+        Self::with_options(tokens, ParserOptions::default())
+    }
+
+    pub fn with_options(mut tokens: Vec<Token>, options: ParserOptions) -> Self {
+        tokens.retain(|token| !token.is_trivia());
         Self {
+            key_origins: intern_keys(&mut tokens, options),
             tokens,
+            spans: Vec::new(),
             current: Cell::new(0),
+            options,
+            recovered: Cell::new(false),
         }
     }
 
-    fn parse(&self) -> Result<Value, ParseError> {
+    /// Takes the tokens of `lexer` along with their spans, so that errors know
+    /// the offset of the token they are about
+    pub fn from_lexer(lexer: Lexer<'_>, options: ParserOptions) -> Self {
+        let (tokens, spans) = lexer
+            .tokens
+            .into_iter()
+            .zip(lexer.spans)
+            .filter(|(token, _)| !token.is_trivia())
+            .unzip();
+        Self {
+            spans,
+            ..Self::with_options(tokens, options)
+        }
+    }
+
+    /// Starts over with `tokens`, keeping the options. The previous tokens are
+    /// returned cleared, so their allocation can go back to a `Lexer` and the two
+    /// vectors are swapped between them for every document
+    pub fn reset(&mut self, mut tokens: Vec<Token>) -> Vec<Token> {
+        tokens.retain(|token| !token.is_trivia());
+        self.spans.clear();
+        self.current.set(0);
+        self.recovered.set(false);
+        self.key_origins = intern_keys(&mut tokens, self.options);
+        let mut previous = core::mem::replace(&mut self.tokens, tokens);
+        previous.clear();
+        previous
+    }
+
+    /// The tokens being parsed, without whitespace and comments. With
+    /// [`ParserOptions::intern_keys`] repeated keys are empty strings here
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    /// The spans of `tokens`, empty unless created with [`Parser::from_lexer`]
+    pub fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+
+    /// Whether the last `parse` had to close containers left open at EOF, see
+    /// [`ParserOptions::recover_truncated`]
+    pub fn recovered(&self) -> bool {
+        self.recovered.get()
+    }
+
+    pub fn parse(&self) -> Result<Value<'_>, ParseError> {
         let value = self.parse_value()?;
-        self.advance();
-        // should be EOF
-        if let Token::EOF(_) = &self.tokens[self.current.get()] {
-            Ok(value)
+        self.expect_eof()?;
+        Ok(value)
+    }
+
+    /// Parses the first value only, ignoring whatever tokens follow it, and
+    /// returns it with the byte offset right after its last token. The offset
+    /// needs the spans, so the parser must come from [`Parser::from_lexer`]
+    pub(crate) fn parse_prefix(&self) -> Result<(Value<'_>, usize), ParseError> {
+        let value = self.parse_value()?;
+        let end = self.spans[self.current.get()].end;
+        Ok((value, end))
+    }
+
+    /// Checks that the tokens form exactly one valid value, like `parse` but
+    /// without building the `Value`
+    pub fn validate(&self) -> Result<(), ParseError> {
+        self._skip_value()?;
+        self.expect_eof()
+    }
+
+    /// Expects the tokens to form a single object and calls `visitor` for every
+    /// top-level field whose key is in `fields`, with its value as a [`Scalar`].
+    /// The values of the other fields are skipped without building them
+    pub fn visit_fields(
+        &self,
+        fields: &[&str],
+        visitor: &mut impl FieldVisitor,
+    ) -> Result<(), ParseError> {
+        self.check(|token| matches!(token, Token::LeftBrace(_)), "'{'")?;
+        if let Token::RightBrace(_) = &self.tokens[self.current.get() + 1] {
+            self.advance();
+            return self.expect_eof();
+        }
+        loop {
+            self.advance();
+            let key = match &self.tokens[self.current.get()] {
+                Token::RightBrace(_) => return Err(self.trailing_comma()),
+                Token::Comma(_) => return Err(self.unexpected_comma()),
+                Token::Str(s, _) => self.key_at(self.current.get(), s),
+                token => {
+                    return Err(ParseError::InvalidKey(
+                        format!("Expected string for key, got {}", token),
+                        self.offset(),
+                    ));
+                }
+            };
+            self.expect(|token| matches!(token, Token::Colon(_)), "':'")?;
+            self.advance();
+            if fields.contains(&key) {
+                let value = match &self.tokens[self.current.get()] {
+                    Token::Str(s, _) => Scalar::Str(s),
+                    Token::Number(n, _) => Scalar::Number(*n),
+                    Token::Bool(b, _) => Scalar::Bool(*b),
+                    Token::Null(_) => Scalar::Null,
+                    token => {
+                        return Err(ParseError::UnexpectedToken(
+                            format!("Expected a scalar for field '{}', got {}", key, token),
+                            self.offset(),
+                        ));
+                    }
+                };
+                visitor.visit_field(key, value);
+            } else {
+                self._skip_value()?;
+            }
+            self.advance();
+            if !matches!(self.tokens[self.current.get()], Token::Comma(_)) {
+                break;
+            }
+        }
+        self.check(|token| matches!(token, Token::RightBrace(_)), "'}'")?;
+        self.expect_eof()
+    }
+
+    /// Expects the tokens to form a single array and calls `f` with every element
+    /// in order. Each element is dropped by `f` before the next one is parsed, so
+    /// only one of them is built at a time
+    pub fn for_each_array_element<'p>(
+        &'p self,
+        f: impl FnMut(Value<'p>),
+    ) -> Result<(), ParseError> {
+        self.check(|token| matches!(token, Token::LeftBracket(_)), "'['")?;
+        if let Token::RightBracket(_) = &self.tokens[self.current.get() + 1] {
+            self.advance();
         } else {
-            Err(ParseError::UnexpectedToken(format!(
-                "Expected EOF, got {}",
-                &self.tokens[self.current.get()]
-            )))
+            self.parse_elements(f)?;
+        }
+        self.expect_eof()
+    }
+
+    fn expect_eof(&self) -> Result<(), ParseError> {
+        self.advance();
+        // should be EOF, anything else starts a second top-level value
+        match &self.tokens[self.current.get()] {
+            Token::EOF(_) => Ok(()),
+            Token::Colon(_) => Err(self.unexpected_colon()),
+            token => Err(ParseError::TrailingData {
+                line: token.line(),
+                offset: self.offset(),
+            }),
+        }
+    }
+
+    /// Index of the token the parser is at, counted without trivia tokens
+    pub fn position(&self) -> usize {
+        self.current.get()
+    }
+
+    /// Consumes exactly one value starting at the current token, including nested
+    /// containers, and moves to the token right after it. The value is checked
+    /// against the grammar but never built, which is the building block for
+    /// custom extractors
+    pub fn skip_value(&self) -> Result<(), ParseError> {
+        self._skip_value()?;
+        self.advance();
+        Ok(())
+    }
+
+    /// Same as `skip_value` but leaves `current` at the last token of the value,
+    /// like `parse_value`
+    fn _skip_value(&self) -> Result<(), ParseError> {
+        match &self.tokens[self.current.get()] {
+            Token::Str(..) | Token::Bool(..) | Token::Number(..) | Token::Null(_) => Ok(()),
+            Token::LeftBracket(_) => {
+                if let Token::RightBracket(_) = &self.tokens[self.current.get() + 1] {
+                    self.advance();
+                    return Ok(());
+                }
+                loop {
+                    self.advance();
+                    if let Token::RightBracket(_) = &self.tokens[self.current.get()] {
+                        return Err(self.trailing_comma());
+                    }
+                    self._skip_value()?;
+                    self.advance();
+                    if !matches!(self.tokens[self.current.get()], Token::Comma(_)) {
+                        break;
+                    }
+                }
+                self.check(|token| matches!(token, Token::RightBracket(_)), "']'")?;
+                Ok(())
+            }
+            Token::LeftBrace(_) => {
+                if let Token::RightBrace(_) = &self.tokens[self.current.get() + 1] {
+                    self.advance();
+                    return Ok(());
+                }
+                loop {
+                    self.advance();
+                    match &self.tokens[self.current.get()] {
+                        Token::RightBrace(_) => return Err(self.trailing_comma()),
+                        Token::Comma(_) => return Err(self.unexpected_comma()),
+                        Token::Str(..) => {}
+                        token => {
+                            return Err(ParseError::InvalidKey(
+                                format!("Expected string for key, got {}", token),
+                                self.offset(),
+                            ));
+                        }
+                    }
+                    self.expect(|token| matches!(token, Token::Colon(_)), "':'")?;
+                    self.advance();
+                    self._skip_value()?;
+                    self.advance();
+                    if !matches!(self.tokens[self.current.get()], Token::Comma(_)) {
+                        break;
+                    }
+                }
+                self.check(|token| matches!(token, Token::RightBrace(_)), "'}'")?;
+                Ok(())
+            }
+            Token::Colon(_) => Err(self.unexpected_colon()),
+            Token::Comma(_) => Err(self.unexpected_comma()),
+            unexpected_token => Err(ParseError::UnexpectedToken(
+                format!("Unexpected token {}", unexpected_token),
+                self.offset(),
+            )),
         }
     }
 
     /// `dict = "{" pair ("," pair)* "}"`
     /// `pair = string ":" value`
-    fn parse_dict(&self) -> Result<HashMap<&str, Value>, ParseError> {
-        let mut result: HashMap<&str, Value> = HashMap::new();
+    fn parse_dict(&self) -> Result<Map<'_>, ParseError> {
+        let mut result = Map::new();
         loop {
             self.advance();
-            if let Token::Str(s, _) = &self.tokens[self.current.get()] {
-                self.advance();
-                if let Token::Colon(_) = &self.tokens[self.current.get()] {
-                    self.advance();
-                } else {
-                    return Err(ParseError::UnexpectedToken(format!(
-                        "Expected ':', got {}",
-                        &self.tokens[self.current.get()]
-                    )));
+            // the first pair can't be '}' as empty dicts are handled by `parse_value`,
+            // so this must follow a comma
+            if let Token::RightBrace(_) = &self.tokens[self.current.get()] {
+                return Err(self.trailing_comma());
+            }
+            if self.recover_at_eof() {
+                return Ok(result);
+            }
+            if let Token::Str(s, line) = &self.tokens[self.current.get()] {
+                let s = self.key_at(self.current.get(), s);
+                if let Some(limit) = self.options.max_object_keys {
+                    // a repeated key only adds a pair when duplicates are kept
+                    let adds_key = self.options.keep_duplicate_keys || !result.contains_key(s);
+                    if adds_key && result.len() >= limit {
+                        return Err(ParseError::TooManyKeys {
+                            limit,
+                            line: *line,
+                            offset: self.offset(),
+                        });
+                    }
+                }
+                match self.expect(|token| matches!(token, Token::Colon(_)), "':'") {
+                    Ok(_) => {
+                        self.advance();
+                        // the key has no value, so it's dropped
+                        if self.recover_at_eof() {
+                            return Ok(result);
+                        }
+                    }
+                    Err(_) if self.recover_at_eof() => return Ok(result),
+                    Err(error) => return Err(error),
                 }
                 let value = self.parse_value()?;
                 self.advance();
-                result.insert(s, value);
+                if self.options.keep_duplicate_keys {
+                    result.append(Cow::Borrowed(s), value);
+                } else {
+                    result.insert(Cow::Borrowed(s), value);
+                }
+            } else if let Token::Comma(_) = self.tokens[self.current.get()] {
+                return Err(self.unexpected_comma());
             } else {
-                return Err(ParseError::InvalidKey(format!(
-                    "Expected string for key, got {}",
-                    &self.tokens[self.current.get()]
-                )));
+                return Err(ParseError::InvalidKey(
+                    format!(
+                        "Expected string for key, got {}",
+                        &self.tokens[self.current.get()]
+                    ),
+                    self.offset(),
+                ));
             }
             if !matches!(self.tokens[self.current.get()], Token::Comma(_)) {
                 break;
             }
         }
-        if let Token::RightBrace(_) = &self.tokens[self.current.get()] {
-            Ok(result)
-        } else {
-            Err(ParseError::UnexpectedToken(format!(
-                "Expected '}}', got {}",
-                &self.tokens[self.current.get()]
-            )))
+        if !self.recover_at_eof() {
+            self.check(|token| matches!(token, Token::RightBrace(_)), "'}'")?;
         }
+        Ok(result)
     }
 
     /// `list = "["value ("," value)*"]"`
-    fn parse_list(&self) -> Result<Vec<Value>, ParseError> {
+    fn parse_list(&self) -> Result<Vec<Value<'_>>, ParseError> {
         let mut result = Vec::new();
+        self.parse_elements(|value| result.push(value))?;
+        Ok(result)
+    }
+
+    /// Same as `parse_list` but hands every element to `f` instead of collecting
+    /// them
+    fn parse_elements<'p>(&'p self, mut f: impl FnMut(Value<'p>)) -> Result<(), ParseError> {
         loop {
             self.advance();
-            let value = self.parse_value()?;
-            result.push(value);
+            // same as in `parse_dict`, this must follow a comma
+            if let Token::RightBracket(_) = &self.tokens[self.current.get()] {
+                return Err(self.trailing_comma());
+            }
+            if self.recover_at_eof() {
+                return Ok(());
+            }
+            f(self.parse_value()?);
             self.advance();
             if !matches!(self.tokens[self.current.get()], Token::Comma(_)) {
                 break;
             }
         }
 
-        if let Token::RightBracket(_) = &self.tokens[self.current.get()] {
-            Ok(result)
-        } else {
-            Err(ParseError::UnexpectedToken(format!(
-                "Expected ']', got {}",
-                &self.tokens[self.current.get()]
-            )))
+        if !self.recover_at_eof() {
+            self.check(|token| matches!(token, Token::RightBracket(_)), "']'")?;
+        }
+        Ok(())
+    }
+
+    /// The key token at `index` with text `key`, which with `intern_keys` comes
+    /// from the first key with the same text
+    fn key_at<'p>(&'p self, index: usize, key: &'p str) -> &'p str {
+        match self
+            .key_origins
+            .get(index)
+            .map(|&origin| &self.tokens[origin])
+        {
+            Some(Token::Str(first, _)) => first,
+            _ => key,
+        }
+    }
+
+    /// Advances to the next token and returns it if `pred` accepts it, otherwise
+    /// fails with "Expected {what}, got {token}"
+    fn expect(&self, pred: impl Fn(&Token) -> bool, what: &str) -> Result<&Token, ParseError> {
+        self.advance();
+        self.check(pred, what)
+    }
+
+    /// Same as `expect` but for the token at `current`, without advancing. A
+    /// stray colon gets its own error
+    fn check(&self, pred: impl Fn(&Token) -> bool, what: &str) -> Result<&Token, ParseError> {
+        match &self.tokens[self.current.get()] {
+            token if pred(token) => Ok(token),
+            Token::Colon(_) => Err(self.unexpected_colon()),
+            token => Err(ParseError::UnexpectedToken(
+                format!("Expected {}, got {}", what, token),
+                self.offset(),
+            )),
         }
     }
 
+    /// Offset of the token at `current`, for errors
+    fn offset(&self) -> Option<usize> {
+        self.spans.get(self.current.get()).map(|span| span.start)
+    }
+
     fn advance(&self) {
         self.current.set(self.current.get() + 1);
     }
 
+    /// With `recover_truncated`, treats EOF at `current` as the end of the open
+    /// container. `current` is moved back to the token before EOF, so the callers
+    /// advancing past the container land on EOF again and recover as well
+    fn recover_at_eof(&self) -> bool {
+        if !self.options.recover_truncated
+            || !matches!(self.tokens[self.current.get()], Token::EOF(_))
+        {
+            return false;
+        }
+        self.recovered.set(true);
+        self.current.set(self.current.get() - 1);
+        true
+    }
+
+    /// error for a closing bracket at `current` that directly follows a comma
+    fn trailing_comma(&self) -> ParseError {
+        let comma = self.current.get() - 1;
+        ParseError::TrailingComma {
+            line: self.tokens[comma].line(),
+            offset: self.spans.get(comma).map(|span| span.start),
+        }
+    }
+
+    /// error for a colon at `current` where a value, a comma or a closing bracket
+    /// should be
+    fn unexpected_colon(&self) -> ParseError {
+        ParseError::UnexpectedColon {
+            line: self.tokens[self.current.get()].line(),
+            offset: self.offset(),
+        }
+    }
+
+    /// error for a comma at `current` where an element or a pair should start
+    fn unexpected_comma(&self) -> ParseError {
+        ParseError::UnexpectedComma {
+            line: self.tokens[self.current.get()].line(),
+            offset: self.offset(),
+        }
+    }
+
     /// `value = dict | list | string | number | "true" | "false" | "null"`
-    fn parse_value(&self) -> Result<Value, ParseError> {
+    fn parse_value(&self) -> Result<Value<'_>, ParseError> {
         match &self.tokens[self.current.get()] {
             // atoms
-            Token::Str(s, _) => Ok(Value::Str(s)),
+            Token::Str(s, _) => Ok(Value::Str(Cow::Borrowed(s))),
             Token::Bool(b, _) => Ok(Value::Bool(*b)),
             Token::Number(n, _) => Ok(Value::Number(*n)),
             Token::Null(_) => Ok(Value::Null),
@@ -186,19 +677,51 @@ impl Parser {
                 // handle empty dict
                 if let Token::RightBrace(_) = &self.tokens[self.current.get() + 1] {
                     self.advance();
-                    Ok(Value::Dict(HashMap::new()))
+                    Ok(Value::Dict(Map::new()))
                 } else {
                     Ok(Value::Dict(self.parse_dict()?))
                 }
             }
-            unexpected_token => Err(ParseError::UnexpectedToken(format!(
-                "Unexpected token {}",
-                unexpected_token
-            ))),
+            Token::Colon(_) => Err(self.unexpected_colon()),
+            Token::Comma(_) => Err(self.unexpected_comma()),
+            unexpected_token => Err(ParseError::UnexpectedToken(
+                format!("Unexpected token {}", unexpected_token),
+                self.offset(),
+            )),
         }
     }
 }
 
+// the first token of every key, a `BTreeMap` without `std` like the index of `Map`
+#[cfg(feature = "std")]
+type KeyOrigins<'a> = std::collections::HashMap<&'a str, usize>;
+#[cfg(not(feature = "std"))]
+type KeyOrigins<'a> = alloc::collections::BTreeMap<&'a str, usize>;
+
+/// For [`ParserOptions::intern_keys`], maps every key token (a string followed by
+/// a colon) to the first key token with the same text, and the other tokens to
+/// themselves. The text of the repeated key tokens is freed, so only the first
+/// one of every key keeps an allocation
+fn intern_keys(tokens: &mut [Token], options: ParserOptions) -> Vec<usize> {
+    if !options.intern_keys {
+        return Vec::new();
+    }
+    let mut first = KeyOrigins::new();
+    let origins: Vec<usize> = (0..tokens.len())
+        .map(|i| match (&tokens[i], tokens.get(i + 1)) {
+            (Token::Str(key, _), Some(Token::Colon(_))) => *first.entry(key).or_insert(i),
+            _ => i,
+        })
+        .collect();
+    for (i, token) in tokens.iter_mut().enumerate() {
+        match token {
+            Token::Str(key, _) if origins[i] != i => *key = String::new(),
+            _ => {}
+        }
+    }
+    origins
+}
+
 #[test]
 fn test_string() {
     use crate::lexer::Lexer;
@@ -207,7 +730,7 @@ fn test_string() {
     lexer.lex().unwrap();
     let parser = Parser::new(lexer.tokens);
     let value = parser.parse().unwrap();
-    assert_eq!(value, Value::Str("test"));
+    assert_eq!(value, Value::Str("test".into()));
 }
 
 #[test]
@@ -240,14 +763,14 @@ fn test_list() {
     lexer.lex().unwrap();
     let parser = Parser::new(lexer.tokens);
     let value = parser.parse().unwrap();
-    let mut hashmap = HashMap::new();
-    hashmap.insert("test", Value::Str("test_list"));
+    let mut hashmap = Map::new();
+    hashmap.insert("test".into(), Value::Str("test_list".into()));
     assert_eq!(
         value,
         Value::List(vec![
             Value::Number(1.0),
             Value::Number(2.2),
-            Value::Str("string"),
+            Value::Str("string".into()),
             Value::Dict(hashmap)
         ])
     );
@@ -280,29 +803,454 @@ fn test_nested_structures() {
     let parser = Parser::new(lexer.tokens);
     let parsed = parser.parse().unwrap();
 
-    let mut expected_map = std::collections::HashMap::new();
+    let mut expected_map = Map::new();
 
-    expected_map.insert("description", Value::Str("The test case description"));
+    expected_map.insert(
+        "description".into(),
+        Value::Str("The test case description".into()),
+    );
 
-    let mut schema_map = std::collections::HashMap::new();
-    schema_map.insert("type", Value::Str("string"));
-    expected_map.insert("schema", Value::Dict(schema_map));
+    let mut schema_map = Map::new();
+    schema_map.insert("type".into(), Value::Str("string".into()));
+    expected_map.insert("schema".into(), Value::Dict(schema_map));
 
-    let mut test1 = std::collections::HashMap::new();
-    test1.insert("description", Value::Str("a test with a valid instance"));
-    test1.insert("data", Value::Str("a string"));
-    test1.insert("valid", Value::Bool(true));
+    let mut test1 = Map::new();
+    test1.insert(
+        "description".into(),
+        Value::Str("a test with a valid instance".into()),
+    );
+    test1.insert("data".into(), Value::Str("a string".into()));
+    test1.insert("valid".into(), Value::Bool(true));
 
-    let mut test2 = std::collections::HashMap::new();
-    test2.insert("description", Value::Str("a test with an invalid instance"));
-    test2.insert("data", Value::Number(15.0));
-    test2.insert("valid", Value::Bool(false));
+    let mut test2 = Map::new();
+    test2.insert(
+        "description".into(),
+        Value::Str("a test with an invalid instance".into()),
+    );
+    test2.insert("data".into(), Value::Number(15.0));
+    test2.insert("valid".into(), Value::Bool(false));
 
     let test_list = vec![Value::Dict(test1), Value::Dict(test2)];
 
-    expected_map.insert("tests", Value::List(test_list));
+    expected_map.insert("tests".into(), Value::List(test_list));
 
     let expected = Value::Dict(expected_map);
 
     assert_eq!(parsed, expected);
 }
+
+#[test]
+fn test_pretty_print_escaped_string() {
+    assert_eq!(pretty_print(&Value::Str("a\"b".into())), r#""a\"b""#);
+    assert_eq!(
+        pretty_print(&Value::Str("back\\slash\nnew\tline\u{1}".into())),
+        r#""back\\slash\nnew\tline\u0001""#
+    );
+}
+
+#[test]
+fn test_pretty_print_empty_containers() {
+    assert_eq!(pretty_print(&Value::Dict(Map::new())), "()");
+    assert_eq!(pretty_print(&Value::List(Vec::new())), "()");
+
+    let mut map = Map::new();
+    map.insert("empty".into(), Value::List(Vec::new()));
+    assert_eq!(pretty_print(&Value::Dict(map)), "(\n  (empty ())\n)");
+}
+
+#[test]
+fn test_trailing_comma() {
+    use crate::lexer::Lexer;
+    let mut lexer = Lexer::new("[1, 2,]");
+    lexer.lex().unwrap();
+    let parser = Parser::new(lexer.tokens);
+    assert!(matches!(
+        parser.parse(),
+        Err(ParseError::TrailingComma { line: 1, .. })
+    ));
+
+    let mut lexer = Lexer::new("{\n  \"a\": 1,\n  \"b\": 2,\n}");
+    lexer.lex().unwrap();
+    let parser = Parser::new(lexer.tokens);
+    assert!(matches!(
+        parser.parse(),
+        Err(ParseError::TrailingComma { line: 3, .. })
+    ));
+}
+
+#[test]
+fn test_trailing_data() {
+    use crate::lexer::Lexer;
+    let mut lexer = Lexer::new("true false");
+    lexer.lex().unwrap();
+    let parser = Parser::new(lexer.tokens);
+    assert!(matches!(
+        parser.parse(),
+        Err(ParseError::TrailingData { line: 1, .. })
+    ));
+
+    let mut lexer = Lexer::new("{\"a\": 1}\n\n[2]");
+    lexer.lex().unwrap();
+    let parser = Parser::new(lexer.tokens);
+    assert!(matches!(
+        parser.parse(),
+        Err(ParseError::TrailingData { line: 3, .. })
+    ));
+}
+
+#[test]
+fn test_top_level_scalar_with_comments() {
+    use crate::lexer::{Lexer, LexerOptions};
+    let options = LexerOptions {
+        allow_comments: true,
+        ..Default::default()
+    };
+    let mut lexer = Lexer::with_options("/* note */ 42 // the answer\n", options);
+    lexer.lex().unwrap();
+    let parser = Parser::new(lexer.tokens);
+    let value = parser.parse().unwrap();
+    assert_eq!(value, Value::Number(42.0));
+}
+
+#[test]
+fn test_recover_truncated() {
+    use crate::lexer::Lexer;
+    let options = ParserOptions {
+        recover_truncated: true,
+        ..Default::default()
+    };
+
+    let mut lexer = Lexer::new("{\"a\":[1,2");
+    lexer.lex().unwrap();
+    let parser = Parser::with_options(lexer.tokens, options);
+    let value = parser.parse().unwrap();
+    let mut expected = Map::new();
+    expected.insert(
+        "a".into(),
+        Value::List(vec![Value::Number(1.0), Value::Number(2.0)]),
+    );
+    assert_eq!(value, Value::Dict(expected));
+    assert!(parser.recovered());
+
+    for (source, expected) in [
+        ("[1, [2, {\"b\": ", "[1,[2,{}]]"),
+        ("{\"a\": 1, \"b\"", "{\"a\":1}"),
+        ("[[[", "[[[]]]"),
+        ("[1,", "[1]"),
+    ] {
+        let mut lexer = Lexer::new(source);
+        lexer.lex().unwrap();
+        let parser = Parser::with_options(lexer.tokens, options);
+        assert_eq!(parser.parse().unwrap().to_string(), expected);
+        assert!(parser.recovered());
+    }
+
+    let mut lexer = Lexer::new("{\"a\":[1,2]}");
+    lexer.lex().unwrap();
+    let parser = Parser::with_options(lexer.tokens, options);
+    parser.parse().unwrap();
+    assert!(!parser.recovered());
+
+    let mut lexer = Lexer::new("{\"a\":[1,2");
+    lexer.lex().unwrap();
+    let parser = Parser::new(lexer.tokens);
+    assert!(parser.parse().is_err());
+}
+
+#[test]
+fn test_parse_ignores_trivia() {
+    use crate::lexer::{Lexer, LexerOptions};
+    let options = LexerOptions {
+        allow_comments: true,
+        preserve_trivia: true,
+        ..Default::default()
+    };
+    let mut lexer = Lexer::with_options(" /* note */ [1, // one\n 2] ", options);
+    lexer.lex().unwrap();
+    let parser = Parser::new(lexer.tokens);
+    assert_eq!(
+        parser.parse().unwrap(),
+        Value::List(vec![Value::Number(1.0), Value::Number(2.0)])
+    );
+}
+
+#[test]
+fn test_keep_duplicate_keys() {
+    use crate::lexer::Lexer;
+    let mut lexer = Lexer::new("{\"a\":1,\"a\":2}");
+    lexer.lex().unwrap();
+    let options = ParserOptions {
+        keep_duplicate_keys: true,
+        ..Default::default()
+    };
+    let parser = Parser::with_options(lexer.tokens, options);
+    let value = parser.parse().unwrap();
+    assert_eq!(
+        value.ordered_entries().unwrap(),
+        [
+            ("a".into(), Value::Number(1.0)),
+            ("a".into(), Value::Number(2.0))
+        ]
+    );
+    assert_eq!(value.to_string(), "{\"a\":1,\"a\":2}");
+    assert_eq!(value.clone().into_owned().to_string(), "{\"a\":1,\"a\":2}");
+
+    let mut lexer = Lexer::new("{\"a\":1,\"a\":2}");
+    lexer.lex().unwrap();
+    let parser = Parser::new(lexer.tokens);
+    assert_eq!(parser.parse().unwrap().to_string(), "{\"a\":2}");
+}
+
+#[test]
+fn test_escaped_keys() {
+    let value = crate::parse_str(r#"{"\u006b": 1, "caf\u00e9": {"\"q\"": true}}"#).unwrap();
+    let Value::Dict(map) = &value else {
+        panic!("expected a dict")
+    };
+    assert_eq!(map["k"], Value::Number(1.0));
+    assert_eq!(value.pointer("/café/\"q\""), Some(&Value::Bool(true)));
+    assert_eq!(value.to_string(), r#"{"k":1,"café":{"\"q\"":true}}"#);
+}
+
+#[test]
+fn test_skip_value() {
+    let mut lexer = crate::Lexer::new(r#"[{"a": {"b": [1, {}]}, "c": null}, 2]"#);
+    lexer.lex().unwrap();
+    let parser = Parser::new(lexer.tokens);
+    parser.advance();
+    parser.skip_value().unwrap();
+    assert_eq!(parser.position(), 19);
+    assert!(matches!(
+        parser.tokens()[parser.position()],
+        Token::Comma(_)
+    ));
+    parser.advance();
+    parser.skip_value().unwrap();
+    assert!(matches!(
+        parser.tokens()[parser.position()],
+        Token::RightBracket(_)
+    ));
+
+    let mut lexer = crate::Lexer::new(r#"{"a": [1,]}"#);
+    lexer.lex().unwrap();
+    let parser = Parser::new(lexer.tokens);
+    assert!(matches!(
+        parser.skip_value(),
+        Err(ParseError::TrailingComma { line: 1, .. })
+    ));
+}
+
+#[test]
+fn test_bare_words_as_strings() {
+    let source = r#"{"status": ok, "code": 200, "done": true}"#;
+    let mut lexer = crate::Lexer::new(source);
+    assert!(matches!(
+        lexer.lex(),
+        Err(crate::LexError::UnknownLiteral(..))
+    ));
+
+    let options = crate::LexerOptions {
+        bare_words_as_strings: true,
+        ..Default::default()
+    };
+    let mut lexer = crate::Lexer::with_options(source, options);
+    lexer.lex().unwrap();
+    let parser = Parser::new(lexer.tokens);
+    let value = parser.parse().unwrap();
+    assert_eq!(value.pointer("/status"), Some(&Value::Str("ok".into())));
+    assert_eq!(value.pointer("/done"), Some(&Value::Bool(true)));
+}
+
+#[test]
+fn test_parser_reset() {
+    let documents = [r#"{"a": [1, 2]}"#, r#"["b", null]"#];
+    let mut lexer = crate::Lexer::new(documents[0]);
+    lexer.lex().unwrap();
+    let mut parser = Parser::new(core::mem::take(&mut lexer.tokens));
+    assert_eq!(parser.parse().unwrap().to_string(), r#"{"a":[1,2]}"#);
+
+    lexer.reset(documents[1]);
+    lexer.lex().unwrap();
+    lexer.tokens = parser.reset(core::mem::take(&mut lexer.tokens));
+    assert!(lexer.tokens.is_empty());
+    assert!(lexer.tokens.capacity() > 0);
+    assert_eq!(parser.parse().unwrap().to_string(), r#"["b",null]"#);
+}
+
+#[test]
+fn test_numeric_equality() {
+    let parse = |s: &str| crate::parse_str(s).unwrap();
+    assert_eq!(parse("5"), parse("5.0"));
+    assert_eq!(parse("5"), parse("5e0"));
+    assert_eq!(parse("[0.5, -0]"), parse("[5e-1, 0]"));
+    assert_eq!(parse("9007199254740992"), parse("9007199254740993"));
+    assert_ne!(parse("5"), parse("5.000001"));
+    assert_ne!(parse("5"), parse("\"5\""));
+    assert_ne!(Value::Number(f64::NAN), Value::Number(f64::NAN));
+}
+
+#[test]
+fn test_max_object_keys() {
+    let options = ParserOptions {
+        max_object_keys: Some(2),
+        ..Default::default()
+    };
+    let parse = |source: &str| {
+        let mut lexer = crate::Lexer::new(source);
+        lexer.lex().unwrap();
+        Parser::from_lexer(lexer, options).parse().map(|_| ())
+    };
+    assert!(parse(r#"{"a": 1, "b": {"c": 2, "d": 3}, "a": 4}"#).is_ok());
+    assert!(parse(r#"[{"a": 1, "b": 2}, {"c": 3}]"#).is_ok());
+
+    let error = parse("{\"a\": 1,\n \"b\": 2,\n \"c\": 3}").unwrap_err();
+    assert!(matches!(
+        error,
+        ParseError::TooManyKeys {
+            limit: 2,
+            line: 3,
+            offset: Some(19)
+        }
+    ));
+    assert_eq!(error.to_string(), "Object has more than 2 keys at line: 3");
+}
+
+#[test]
+fn test_unexpected_colon() {
+    for (source, line) in [
+        ("[1:2]", 1),
+        (":", 1),
+        ("[\n:]", 2),
+        ("{\"a\": 1: 2}", 1),
+        ("1: 2", 1),
+        ("[1, [2]:]", 1),
+    ] {
+        let mut lexer = crate::Lexer::new(source);
+        lexer.lex().unwrap();
+        let tokens = lexer.tokens;
+        let parser = Parser::new(tokens);
+        let error = parser.parse().unwrap_err();
+        assert!(
+            matches!(error, ParseError::UnexpectedColon { line: l, .. } if l == line),
+            "{}: {:?}",
+            source,
+            error
+        );
+        assert!(crate::validate_syntax(source).is_err());
+    }
+    assert!(matches!(
+        crate::validate_syntax("[1:2]"),
+        Err(crate::JsonError::Parse(ParseError::UnexpectedColon {
+            line: 1,
+            offset: Some(2)
+        }))
+    ));
+    assert_eq!(
+        crate::parse_str("[1:2]").unwrap_err().to_string(),
+        "Unexpected ':' at line: 1, colons only separate keys from values in objects"
+    );
+}
+
+#[test]
+fn test_intern_keys() {
+    let records: Vec<String> = (0..1000)
+        .map(|i| format!(r#"{{"id": {}, "name": "n{}", "tags": ["name"]}}"#, i, i))
+        .collect();
+    let source = format!("[{}]", records.join(","));
+    let options = ParserOptions {
+        intern_keys: true,
+        ..Default::default()
+    };
+    let mut lexer = crate::Lexer::new(&source);
+    lexer.lex().unwrap();
+    let parser = Parser::from_lexer(lexer, options);
+    let value = parser.parse().unwrap();
+    assert_eq!(value, crate::parse_str(&source).unwrap());
+    // the parser holds the text of the three keys once
+    let held: Vec<&str> = parser
+        .tokens()
+        .windows(2)
+        .filter_map(|pair| match pair {
+            [Token::Str(key, _), Token::Colon(_)] if key.capacity() > 0 => Some(key.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(held, ["id", "name", "tags"]);
+
+    let Value::List(list) = &value else {
+        panic!("expected a list")
+    };
+    assert_eq!(list.len(), 1000);
+    let maps: Vec<&Map> = list
+        .iter()
+        .map(|record| match record {
+            Value::Dict(map) => map,
+            _ => panic!("expected a dict"),
+        })
+        .collect();
+    let key_ptrs = |map: &Map| map.keys().map(|key| key.as_ptr()).collect::<Vec<_>>();
+    let first_keys = key_ptrs(maps[0]);
+    for map in &maps {
+        assert_eq!(key_ptrs(map), first_keys);
+    }
+    // string values never get interned
+    let tag_ptr = |map: &Map| match &map["tags"] {
+        Value::List(tags) => match &tags[0] {
+            Value::Str(tag) => tag.as_ptr(),
+            _ => panic!("expected a string"),
+        },
+        _ => panic!("expected a list"),
+    };
+    assert_ne!(tag_ptr(maps[0]), tag_ptr(maps[1]));
+}
+
+#[test]
+fn test_missing_colon_message() {
+    let source = r#"{"a": {"b" 1}}"#;
+    let mut lexer = crate::Lexer::new(source);
+    lexer.lex().unwrap();
+    let parser = Parser::from_lexer(lexer, ParserOptions::default());
+    let error = parser.parse().unwrap_err();
+    assert!(matches!(error, ParseError::UnexpectedToken(_, Some(11))));
+    assert_eq!(error.to_string(), "Expected ':', got '1' at line: 1");
+
+    // skipping reports the same error
+    let error = crate::validate_syntax(source).unwrap_err();
+    assert_eq!(error.to_string(), "Expected ':', got '1' at line: 1");
+}
+
+#[test]
+fn test_unexpected_comma() {
+    for (source, offset) in [
+        ("[1,,2]", 3),
+        ("{\"a\":1,,\"b\":2}", 7),
+        ("[,1]", 1),
+        ("{,}", 1),
+        (",", 0),
+    ] {
+        for error in [
+            crate::parse_str(source).unwrap_err(),
+            crate::validate_syntax(source).unwrap_err(),
+        ] {
+            assert!(
+                matches!(
+                    error,
+                    crate::JsonError::Parse(ParseError::UnexpectedComma { line: 1, offset: Some(o) })
+                        if o == offset
+                ),
+                "{}: {:?}",
+                source,
+                error
+            );
+        }
+    }
+    assert_eq!(
+        crate::parse_str("[1,,2]").unwrap_err().to_string(),
+        "Unexpected ',' at line: 1, an element is missing before it"
+    );
+    // a comma before a closing bracket is still a trailing comma
+    assert!(matches!(
+        crate::parse_str("[1,]"),
+        Err(crate::JsonError::Parse(ParseError::TrailingComma { .. }))
+    ));
+}
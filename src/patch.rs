@@ -0,0 +1,352 @@
+use crate::map::Map;
+use crate::parser::{OwnedValue, Value};
+use crate::pointer::{array_index, escape_token, reference_tokens};
+use alloc::borrow::Cow;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// Why [`Value::apply_patch`] rejected a patch
+#[derive(Debug, PartialEq)]
+pub enum PatchError {
+    /// The patch isn't an array of operation objects with the members their `op`
+    /// needs
+    InvalidOperation(String),
+    /// A `path` or `from` that doesn't resolve, or whose parent can't hold it
+    PathNotFound(String),
+    /// A `test` operation whose value didn't match
+    TestFailed(String),
+}
+
+impl core::fmt::Display for PatchError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PatchError::InvalidOperation(message) => write!(f, "{}", message),
+            PatchError::PathNotFound(path) => write!(f, "Path {} not found", path),
+            PatchError::TestFailed(path) => write!(f, "Test failed at path {}", path),
+        }
+    }
+}
+
+impl core::error::Error for PatchError {}
+
+/// The string member `name` of an operation
+fn member<'v>(operation: &'v Map<'_>, name: &str) -> Result<&'v str, PatchError> {
+    match operation.get(name) {
+        Some(Value::Str(s)) => Ok(s),
+        _ => Err(PatchError::InvalidOperation(format!(
+            "Operation is missing the string member '{}'",
+            name
+        ))),
+    }
+}
+
+/// Splits `path` into the tokens of its parent and the last token, `None` for
+/// the root
+fn split_path(path: &str) -> Result<Option<(Vec<String>, String)>, PatchError> {
+    let mut tokens =
+        reference_tokens(path).ok_or_else(|| PatchError::PathNotFound(path.to_string()))?;
+    Ok(tokens.pop().map(|last| (tokens, last)))
+}
+
+fn resolve_mut<'v, 'a>(
+    mut target: &'v mut Value<'a>,
+    tokens: &[String],
+    path: &str,
+) -> Result<&'v mut Value<'a>, PatchError> {
+    let not_found = || PatchError::PathNotFound(path.to_string());
+    for token in tokens {
+        target = match target {
+            Value::Dict(map) => map.get_mut(token).ok_or_else(not_found)?,
+            Value::List(list) => list
+                .get_mut(array_index(token).ok_or_else(not_found)?)
+                .ok_or_else(not_found)?,
+            _ => return Err(not_found()),
+        };
+    }
+    Ok(target)
+}
+
+/// `add`: inserts or replaces a key, inserts into an array before the index or
+/// appends for `-`, and replaces the whole document for the empty path
+fn add<'a>(root: &mut Value<'a>, path: &str, value: Value<'a>) -> Result<(), PatchError> {
+    let Some((parent, last)) = split_path(path)? else {
+        *root = value;
+        return Ok(());
+    };
+    match resolve_mut(root, &parent, path)? {
+        Value::Dict(map) => {
+            map.insert(Cow::Owned(last), value);
+        }
+        Value::List(list) if last == "-" => list.push(value),
+        Value::List(list) => match array_index(&last) {
+            Some(i) if i <= list.len() => list.insert(i, value),
+            _ => return Err(PatchError::PathNotFound(path.to_string())),
+        },
+        _ => return Err(PatchError::PathNotFound(path.to_string())),
+    }
+    Ok(())
+}
+
+/// `remove`: takes the value at `path` out of its parent
+fn remove<'a>(root: &mut Value<'a>, path: &str) -> Result<Value<'a>, PatchError> {
+    let not_found = || PatchError::PathNotFound(path.to_string());
+    let Some((parent, last)) = split_path(path)? else {
+        return Ok(core::mem::replace(root, Value::Null));
+    };
+    match resolve_mut(root, &parent, path)? {
+        Value::Dict(map) => map.remove(&last).ok_or_else(not_found),
+        Value::List(list) => match array_index(&last) {
+            Some(i) if i < list.len() => Ok(list.remove(i)),
+            _ => Err(not_found()),
+        },
+        _ => Err(not_found()),
+    }
+}
+
+impl<'a> Value<'a> {
+    /// Applies a JSON Patch (RFC 6902), an array of `add`, `remove`, `replace`,
+    /// `move`, `copy` and `test` operations. The patch is applied as a whole: if
+    /// any operation fails `self` is left unchanged
+    pub fn apply_patch(&mut self, patch: &Value<'_>) -> Result<(), PatchError> {
+        let Value::List(operations) = patch else {
+            return Err(PatchError::InvalidOperation(
+                "Patch must be an array of operations".to_string(),
+            ));
+        };
+        let mut result = self.clone();
+        for operation in operations {
+            let Value::Dict(operation) = operation else {
+                return Err(PatchError::InvalidOperation(
+                    "Operation must be an object".to_string(),
+                ));
+            };
+            let path = member(operation, "path")?;
+            let value = || match operation.get("value") {
+                Some(value) => Ok(value.clone().into_owned()),
+                None => Err(PatchError::InvalidOperation(
+                    "Operation is missing the member 'value'".to_string(),
+                )),
+            };
+            match member(operation, "op")? {
+                "add" => add(&mut result, path, value()?)?,
+                "remove" => {
+                    remove(&mut result, path)?;
+                }
+                "replace" => {
+                    let target = result
+                        .pointer_mut(path)
+                        .ok_or_else(|| PatchError::PathNotFound(path.to_string()))?;
+                    *target = value()?;
+                }
+                "move" => {
+                    let from = member(operation, "from")?;
+                    let moved = remove(&mut result, from)?;
+                    add(&mut result, path, moved)?;
+                }
+                "copy" => {
+                    let from = member(operation, "from")?;
+                    let copied = result
+                        .pointer(from)
+                        .ok_or_else(|| PatchError::PathNotFound(from.to_string()))?
+                        .clone();
+                    add(&mut result, path, copied)?;
+                }
+                "test" => {
+                    if result.pointer(path) != Some(&value()?) {
+                        return Err(PatchError::TestFailed(path.to_string()));
+                    }
+                }
+                op => {
+                    return Err(PatchError::InvalidOperation(format!(
+                        "Unknown operation '{}'",
+                        op
+                    )));
+                }
+            }
+        }
+        *self = result;
+        Ok(())
+    }
+}
+
+/// Applies a JSON Merge Patch (RFC 7386): the keys of an object patch are merged
+/// into `target` recursively and a `null` value deletes the key, while any other
+/// patch replaces `target` entirely. A target that isn't an object is replaced
+/// by an empty object before an object patch is merged into it
+pub fn apply_merge_patch<'a>(target: &mut Value<'a>, patch: &Value<'_>) {
+    let Value::Dict(patch) = patch else {
+        *target = patch.clone().into_owned();
+        return;
+    };
+    if !matches!(target, Value::Dict(_)) {
+        *target = Value::new_object();
+    }
+    let Value::Dict(map) = target else {
+        unreachable!()
+    };
+    for (key, value) in patch {
+        if let Value::Null = value {
+            map.remove(key);
+        } else {
+            let target = map.get_mut_or_insert_with(key, || Value::Null);
+            apply_merge_patch(target, value);
+        }
+    }
+}
+
+fn operation(op: &str, path: &str, value: Option<&Value<'_>>) -> OwnedValue {
+    let mut map = Map::new();
+    map.insert("op".into(), Value::Str(Cow::Owned(op.to_string())));
+    map.insert("path".into(), Value::Str(Cow::Owned(path.to_string())));
+    if let Some(value) = value {
+        map.insert("value".into(), value.clone().into_owned());
+    }
+    Value::Dict(map)
+}
+
+fn diff(a: &Value<'_>, b: &Value<'_>, path: &str, patch: &mut Vec<OwnedValue>) {
+    match (a, b) {
+        (Value::Dict(a), Value::Dict(b)) => {
+            for (key, value) in a {
+                let path = format!("{}/{}", path, escape_token(key));
+                match b.get(key) {
+                    Some(other) => diff(value, other, &path, patch),
+                    None => patch.push(operation("remove", &path, None)),
+                }
+            }
+            for (key, value) in b {
+                if !a.contains_key(key) {
+                    let path = format!("{}/{}", path, escape_token(key));
+                    patch.push(operation("add", &path, Some(value)));
+                }
+            }
+        }
+        (Value::List(a), Value::List(b)) => {
+            for (i, (a, b)) in a.iter().zip(b).enumerate() {
+                diff(a, b, &format!("{}/{}", path, i), patch);
+            }
+            for value in b.iter().skip(a.len()) {
+                patch.push(operation("add", &format!("{}/-", path), Some(value)));
+            }
+            // from the end so the indices of the remaining elements don't shift
+            for i in (b.len()..a.len()).rev() {
+                patch.push(operation("remove", &format!("{}/{}", path, i), None));
+            }
+        }
+        (a, b) if a == b => {}
+        (_, b) => patch.push(operation("replace", path, Some(b))),
+    }
+}
+
+/// A JSON Patch (RFC 6902) that turns `a` into `b` when applied with
+/// [`Value::apply_patch`]. Objects and arrays are compared member by member,
+/// arrays by index, so an element inserted in the middle replaces every element
+/// after it
+pub fn diff_as_patch(a: &Value<'_>, b: &Value<'_>) -> OwnedValue {
+    let mut patch = Vec::new();
+    diff(a, b, "", &mut patch);
+    Value::List(patch)
+}
+
+#[test]
+fn test_apply_patch() {
+    let mut value = crate::parse_str(r#"{"a": [1, 2], "b": {"c": "d"}}"#).unwrap();
+    let patch = crate::parse_str(
+        r#"
+        [
+            {"op": "add", "path": "/a/1", "value": 5},
+            {"op": "add", "path": "/a/-", "value": 6},
+            {"op": "remove", "path": "/b/c"},
+            {"op": "replace", "path": "/b", "value": [true]},
+            {"op": "copy", "from": "/a/0", "path": "/e"},
+            {"op": "move", "from": "/b", "path": "/f~1g"},
+            {"op": "test", "path": "/a", "value": [1, 5, 2, 6]}
+        ]
+        "#,
+    )
+    .unwrap();
+    value.apply_patch(&patch).unwrap();
+    assert_eq!(
+        value,
+        crate::parse_str(r#"{"a": [1, 5, 2, 6], "e": 1, "f/g": [true]}"#).unwrap()
+    );
+
+    let before = value.clone();
+    let failing = crate::parse_str(
+        r#"[{"op": "remove", "path": "/e"}, {"op": "test", "path": "/a/0", "value": 2}]"#,
+    )
+    .unwrap();
+    assert_eq!(
+        value.apply_patch(&failing),
+        Err(PatchError::TestFailed("/a/0".to_string()))
+    );
+    assert_eq!(value, before);
+
+    let missing = crate::parse_str(r#"[{"op": "remove", "path": "/x/y"}]"#).unwrap();
+    assert_eq!(
+        value.apply_patch(&missing),
+        Err(PatchError::PathNotFound("/x/y".to_string()))
+    );
+    let invalid = crate::parse_str(r#"[{"op": "jump", "path": ""}]"#).unwrap();
+    assert!(matches!(
+        value.apply_patch(&invalid),
+        Err(PatchError::InvalidOperation(_))
+    ));
+}
+
+#[test]
+fn test_diff_as_patch() {
+    let a = crate::parse_str(crate::value::NESTED_STRUCTURES).unwrap();
+    let b = crate::parse_str(
+        r#"
+        {
+            "description": "The test case description",
+            "schema": { "type": "number", "minimum": 0 },
+            "tests": [
+                {
+                    "description": "a test with a valid instance",
+                    "data": 3,
+                    "valid": true
+                }
+            ],
+            "a/b": null
+        }
+        "#,
+    )
+    .unwrap();
+    let patch = diff_as_patch(&a, &b);
+    let mut patched = a.clone();
+    patched.apply_patch(&patch).unwrap();
+    assert_eq!(patched, b);
+
+    let mut reverse = b.clone();
+    reverse.apply_patch(&diff_as_patch(&b, &a)).unwrap();
+    assert_eq!(reverse, a);
+    assert_eq!(diff_as_patch(&a, &a), Value::new_array());
+}
+
+#[test]
+fn test_apply_merge_patch() {
+    let mut value = crate::parse_str(
+        r#"{"title": "Goodbye!", "author": {"given": "John", "family": "Doe"}, "tags": ["a", "b"]}"#,
+    )
+    .unwrap();
+    let patch = crate::parse_str(
+        r#"{"title": "Hello!", "author": {"family": null}, "tags": ["c"], "phone": {"home": "1", "work": null}}"#,
+    )
+    .unwrap();
+    apply_merge_patch(&mut value, &patch);
+    assert_eq!(
+        value,
+        crate::parse_str(
+            r#"{"title": "Hello!", "author": {"given": "John"}, "tags": ["c"], "phone": {"home": "1"}}"#
+        )
+        .unwrap()
+    );
+
+    apply_merge_patch(&mut value, &crate::parse_str("[1]").unwrap());
+    assert_eq!(value, crate::parse_str("[1]").unwrap());
+    apply_merge_patch(&mut value, &crate::parse_str(r#"{"a": 1}"#).unwrap());
+    assert_eq!(value, crate::parse_str(r#"{"a": 1}"#).unwrap());
+}
@@ -0,0 +1,369 @@
+use crate::parser::{OwnedValue, Value};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+#[derive(Debug, PartialEq)]
+pub enum PathError {
+    /// The expression doesn't start with `$`
+    MissingRoot,
+    InvalidSyntax(String),
+}
+
+impl core::fmt::Display for PathError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PathError::MissingRoot => write!(f, "JSONPath must start with '$'"),
+            PathError::InvalidSyntax(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl core::error::Error for PathError {}
+
+/// One step of a parsed JSONPath expression
+enum Step {
+    Child(String),
+    Index(usize),
+    Wildcard,
+    Filter(Filter),
+}
+
+/// `[?(@.field op literal)]`, keeps the children for which the comparison holds
+struct Filter {
+    fields: Vec<String>,
+    op: Ordering,
+    negate: bool,
+    or_equal: bool,
+    literal: OwnedValue,
+}
+
+impl Filter {
+    fn matches(&self, value: &Value<'_>) -> bool {
+        let mut target = value;
+        for field in &self.fields {
+            match target {
+                Value::Dict(map) => match map.get(field) {
+                    Some(value) => target = value,
+                    None => return false,
+                },
+                _ => return false,
+            }
+        }
+        let ordering = match (target, &self.literal) {
+            (Value::Number(a), Value::Number(b)) => a.partial_cmp(b),
+            (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+            // other values only support `==` and `!=`, not `<=` or `>=`
+            (a, b) if self.op == Ordering::Equal && a == b => Some(Ordering::Equal),
+            // not comparable, only `!=` holds
+            _ => return self.negate,
+        };
+        let Some(ordering) = ordering else {
+            return self.negate;
+        };
+        let holds = ordering == self.op || (self.or_equal && ordering == Ordering::Equal);
+        holds != self.negate
+    }
+}
+
+/// Splits a JSONPath expression into steps, supporting `.name`, `.*`, `[n]`,
+/// `[*]`, `['name']` and `[?(@.field op literal)]`
+fn parse_steps(expr: &str) -> Result<Vec<Step>, PathError> {
+    let mut rest = expr.strip_prefix('$').ok_or(PathError::MissingRoot)?;
+    let mut steps = Vec::new();
+    while !rest.is_empty() {
+        if let Some(after_dot) = rest.strip_prefix('.') {
+            let end = after_dot.find(['.', '[']).unwrap_or(after_dot.len());
+            let name = &after_dot[..end];
+            steps.push(match name {
+                "" => return Err(syntax_error("Expected a name after '.'", expr)),
+                "*" => Step::Wildcard,
+                name => Step::Child(name.to_string()),
+            });
+            rest = &after_dot[end..];
+        } else if let Some(after_bracket) = rest.strip_prefix('[') {
+            let end =
+                closing_bracket(after_bracket).ok_or_else(|| syntax_error("Unclosed '['", expr))?;
+            let inner = after_bracket[..end].trim();
+            steps.push(if inner == "*" {
+                Step::Wildcard
+            } else if let Ok(index) = inner.parse::<usize>() {
+                Step::Index(index)
+            } else if let Some(name) = unquote(inner) {
+                Step::Child(name.to_string())
+            } else if let Some(filter) = inner.strip_prefix("?(").and_then(|f| f.strip_suffix(')'))
+            {
+                Step::Filter(parse_filter(filter.trim(), expr)?)
+            } else {
+                return Err(syntax_error("Unsupported bracket expression", expr));
+            });
+            rest = &after_bracket[end + 1..];
+        } else {
+            return Err(syntax_error("Expected '.' or '['", expr));
+        }
+    }
+    Ok(steps)
+}
+
+fn parse_filter(filter: &str, expr: &str) -> Result<Filter, PathError> {
+    let path = filter
+        .strip_prefix('@')
+        .ok_or_else(|| syntax_error("Filter must start with '@'", expr))?;
+    let op_start = path
+        .find(['=', '!', '<', '>'])
+        .ok_or_else(|| syntax_error("Expected a comparison in the filter", expr))?;
+    let fields = path[..op_start].trim();
+    let fields = if fields.is_empty() {
+        Vec::new()
+    } else {
+        fields
+            .strip_prefix('.')
+            .ok_or_else(|| syntax_error("Expected '.' after '@'", expr))?
+            .split('.')
+            .map(str::to_string)
+            .collect()
+    };
+    let comparison = &path[op_start..];
+    let (op, negate, or_equal, literal) = [
+        ("==", Ordering::Equal, false, false),
+        ("!=", Ordering::Equal, true, false),
+        ("<=", Ordering::Less, false, true),
+        (">=", Ordering::Greater, false, true),
+        ("<", Ordering::Less, false, false),
+        (">", Ordering::Greater, false, false),
+    ]
+    .into_iter()
+    .find_map(|(token, op, negate, or_equal)| {
+        comparison
+            .strip_prefix(token)
+            .map(|literal| (op, negate, or_equal, literal.trim()))
+    })
+    .ok_or_else(|| syntax_error("Unknown comparison in the filter", expr))?;
+    let literal = match unquote(literal) {
+        Some(s) => Value::Str(s.to_string().into()),
+        None => crate::parse_str(literal)
+            .map_err(|_| syntax_error("Invalid literal in the filter", expr))?,
+    };
+    Ok(Filter {
+        fields,
+        op,
+        negate,
+        or_equal,
+        literal,
+    })
+}
+
+/// Position of the `]` closing a bracket, skipping over quoted strings
+fn closing_bracket(s: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, ']') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Contents of a string in single or double quotes
+fn unquote(s: &str) -> Option<&str> {
+    s.strip_prefix('\'')
+        .and_then(|s| s.strip_suffix('\''))
+        .or_else(|| s.strip_prefix('"').and_then(|s| s.strip_suffix('"')))
+}
+
+fn syntax_error(message: &str, expr: &str) -> PathError {
+    PathError::InvalidSyntax(format!("{} in JSONPath '{}'", message, expr))
+}
+
+impl<'a> Value<'a> {
+    /// Looks up every value matching a dotted `path` like `tests.*.valid`. A
+    /// segment selects the object value with that key (or the array element with
+    /// that index), and `*` selects every array element or object value. An empty
+    /// `path` matches the value itself
+    pub fn deep_get(&self, path: &str) -> Vec<&Value<'a>> {
+        let mut matches = vec![self];
+        if path.is_empty() {
+            return matches;
+        }
+        for segment in path.split('.') {
+            matches = matches
+                .into_iter()
+                .flat_map(|value| -> Vec<&Value<'a>> {
+                    match (value, segment) {
+                        (Value::Dict(map), "*") => map.values().collect(),
+                        (Value::List(list), "*") => list.iter().collect(),
+                        (Value::Dict(map), key) => map.get(key).into_iter().collect(),
+                        (Value::List(list), index) => index
+                            .parse::<usize>()
+                            .ok()
+                            .and_then(|i| list.get(i))
+                            .into_iter()
+                            .collect(),
+                        _ => Vec::new(),
+                    }
+                })
+                .collect();
+        }
+        matches
+    }
+
+    /// Evaluates a JSONPath expression like `$.store.book[?(@.price < 10)].title`.
+    /// The supported subset is: the root `$`, children `.name` and `['name']`,
+    /// indices `[0]`, wildcards `.*` and `[*]`, and filters comparing a field of
+    /// each child (`@.a.b`, or `@` itself) to a number, string, bool or null
+    /// literal with `==`, `!=`, `<`, `<=`, `>` or `>=`. Ordering comparisons only
+    /// hold between two numbers or two strings
+    pub fn jsonpath<'b>(&'b self, expr: &str) -> Result<Vec<&'b Value<'a>>, PathError> {
+        let mut matches = vec![self];
+        for step in parse_steps(expr)? {
+            matches = matches
+                .into_iter()
+                .flat_map(|value| -> Vec<&'b Value<'a>> {
+                    let children: Vec<&'b Value<'a>> = match value {
+                        Value::Dict(map) => map.values().collect(),
+                        Value::List(list) => list.iter().collect(),
+                        _ => Vec::new(),
+                    };
+                    match (&step, value) {
+                        (Step::Child(key), Value::Dict(map)) => map.get(key).into_iter().collect(),
+                        (Step::Index(i), Value::List(list)) => list.get(*i).into_iter().collect(),
+                        (Step::Wildcard, _) => children,
+                        (Step::Filter(filter), _) => children
+                            .into_iter()
+                            .filter(|child| filter.matches(child))
+                            .collect(),
+                        _ => Vec::new(),
+                    }
+                })
+                .collect();
+        }
+        Ok(matches)
+    }
+}
+
+#[test]
+fn test_deep_get() {
+    let value = crate::parse_str(crate::value::NESTED_STRUCTURES).unwrap();
+    assert_eq!(
+        value.deep_get("tests.*.description"),
+        [
+            &Value::Str("a test with a valid instance".into()),
+            &Value::Str("a test with an invalid instance".into())
+        ]
+    );
+    assert_eq!(value.deep_get("tests.1.valid"), [&Value::Bool(false)]);
+    assert_eq!(value.deep_get("schema.*"), [&Value::Str("string".into())]);
+    assert_eq!(value.deep_get("tests.*.missing"), Vec::<&Value>::new());
+    assert_eq!(value.deep_get("description.*"), Vec::<&Value>::new());
+    assert_eq!(value.deep_get(""), [&value]);
+}
+
+#[cfg(test)]
+const STORE: &str = r#"
+    {
+        "store": {
+            "book": [
+                {"title": "Sayings of the Century", "price": 8.95, "category": "reference"},
+                {"title": "Sword of Honour", "price": 12.99, "category": "fiction"},
+                {"title": "Moby Dick", "price": 8.99, "category": "fiction"},
+                {"title": "The Lord of the Rings", "price": 22.99, "category": "fiction"}
+            ],
+            "bicycle": {"color": "red", "price": 19.95}
+        }
+    }
+    "#;
+
+#[cfg(test)]
+fn titles(values: Vec<&Value<'_>>) -> Vec<String> {
+    values
+        .into_iter()
+        .map(|value| match value {
+            Value::Dict(map) => map["title"].to_string(),
+            other => other.to_string(),
+        })
+        .collect()
+}
+
+#[test]
+fn test_jsonpath_children_and_wildcards() {
+    let value = crate::parse_str(STORE).unwrap();
+    assert_eq!(value.jsonpath("$").unwrap(), [&value]);
+    assert_eq!(value.jsonpath("$.store.book[*].title").unwrap().len(), 4);
+    assert_eq!(value.jsonpath("$.store.*").unwrap().len(), 2);
+    assert_eq!(
+        titles(value.jsonpath("$['store'].bicycle.color").unwrap()),
+        ["\"red\""]
+    );
+    assert!(value.jsonpath("$.store.missing[*]").unwrap().is_empty());
+}
+
+#[test]
+fn test_jsonpath_index() {
+    let value = crate::parse_str(STORE).unwrap();
+    assert_eq!(
+        titles(value.jsonpath("$.store.book[2]").unwrap()),
+        ["\"Moby Dick\""]
+    );
+    assert!(value.jsonpath("$.store.book[10]").unwrap().is_empty());
+}
+
+#[test]
+fn test_jsonpath_filter() {
+    let value = crate::parse_str(STORE).unwrap();
+    assert_eq!(
+        titles(value.jsonpath("$.store.book[?(@.price < 10)]").unwrap()),
+        ["\"Sayings of the Century\"", "\"Moby Dick\""]
+    );
+    assert_eq!(
+        titles(
+            value
+                .jsonpath("$.store.book[?(@.category == 'fiction')].title")
+                .unwrap()
+        ),
+        [
+            "\"Sword of Honour\"",
+            "\"Moby Dick\"",
+            "\"The Lord of the Rings\""
+        ]
+    );
+    assert_eq!(
+        value
+            .jsonpath("$.store.book[?(@.price >= 22.99)]")
+            .unwrap()
+            .len(),
+        1
+    );
+    assert_eq!(
+        value
+            .jsonpath("$.store.book[?(@.category != \"fiction\")]")
+            .unwrap()
+            .len(),
+        1
+    );
+    let prices = crate::parse_str("[1, 5, 10]").unwrap();
+    assert_eq!(prices.jsonpath("$[?(@ > 4)]").unwrap().len(), 2);
+
+    let flags = crate::parse_str(r#"[{"flag": true}, {"flag": null}]"#).unwrap();
+    assert_eq!(flags.jsonpath("$[?(@.flag == true)]").unwrap().len(), 1);
+    assert_eq!(flags.jsonpath("$[?(@.flag != null)]").unwrap().len(), 1);
+    assert!(flags.jsonpath("$[?(@.flag <= true)]").unwrap().is_empty());
+    assert!(flags.jsonpath("$[?(@.flag >= null)]").unwrap().is_empty());
+}
+
+#[test]
+fn test_jsonpath_errors() {
+    let value = crate::parse_str(STORE).unwrap();
+    assert_eq!(value.jsonpath("store"), Err(PathError::MissingRoot));
+    for expr in ["$.", "$[1", "$.a[?(@.b ~ 1)]", "$.a[?(@.b < x)]", "$x"] {
+        assert!(
+            matches!(value.jsonpath(expr), Err(PathError::InvalidSyntax(_))),
+            "{} should be invalid",
+            expr
+        );
+    }
+}
@@ -0,0 +1,336 @@
+use crate::map::Map;
+use crate::parser::{OwnedValue, Value};
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+#[derive(Debug, PartialEq)]
+pub enum PointerError {
+    /// The pointer is neither empty nor starts with `/`
+    InvalidSyntax(String),
+    /// The pointer is well formed but doesn't resolve to a value
+    NotFound(String),
+}
+
+impl core::fmt::Display for PointerError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PointerError::InvalidSyntax(pointer) => {
+                write!(
+                    f,
+                    "JSON Pointer '{}' must be empty or start with '/'",
+                    pointer
+                )
+            }
+            PointerError::NotFound(pointer) => write!(f, "No value at '{}'", pointer),
+        }
+    }
+}
+
+impl core::error::Error for PointerError {}
+
+/// Splits a JSON Pointer (RFC 6901) into its unescaped reference tokens, `None`
+/// if it's neither empty nor starts with `/`
+pub(crate) fn reference_tokens(pointer: &str) -> Option<Vec<String>> {
+    if pointer.is_empty() {
+        return Some(Vec::new());
+    }
+    let rest = pointer.strip_prefix('/')?;
+    Some(
+        rest.split('/')
+            .map(|token| token.replace("~1", "/").replace("~0", "~"))
+            .collect(),
+    )
+}
+
+/// Escapes `~` and `/` in a key so it can be used as a reference token
+pub(crate) fn escape_token(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Array index of a reference token, leading zeros aren't allowed
+pub(crate) fn array_index(token: &str) -> Option<usize> {
+    if token.is_empty() || (token.len() > 1 && token.starts_with('0')) {
+        return None;
+    }
+    if !token.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+/// Copies the parts of `value` reached by `paths`, a path that ends here selects
+/// the whole value
+fn project_tokens(value: &Value<'_>, paths: &[&[String]]) -> OwnedValue {
+    if paths.iter().any(|path| path.is_empty()) {
+        return value.clone().into_owned();
+    }
+    // the tails of the paths that continue into the child named `token`
+    let tails = |token: &str| -> Vec<&[String]> {
+        paths
+            .iter()
+            .filter(|path| path[0] == token)
+            .map(|path| &path[1..])
+            .collect()
+    };
+    match value {
+        Value::Dict(map) => {
+            Value::Dict(Map::from_entries(map.iter().filter_map(|(key, child)| {
+                let tails = tails(key);
+                (!tails.is_empty())
+                    .then(|| (Cow::Owned(key.to_string()), project_tokens(child, &tails)))
+            })))
+        }
+        Value::List(list) => Value::List(
+            list.iter()
+                .enumerate()
+                .filter_map(|(i, item)| {
+                    let tails = tails(&i.to_string());
+                    (!tails.is_empty()).then(|| project_tokens(item, &tails))
+                })
+                .collect(),
+        ),
+        _ => Value::Null,
+    }
+}
+
+/// Pushes the pointer of every node under `value` (at `pointer`) that satisfies
+/// `pred` into `paths`, parents before their children
+fn collect_paths<F: Fn(&Value<'_>) -> bool>(
+    value: &Value<'_>,
+    pred: &F,
+    pointer: &mut String,
+    paths: &mut Vec<String>,
+) {
+    if pred(value) {
+        paths.push(pointer.clone());
+    }
+    let len = pointer.len();
+    match value {
+        Value::Dict(map) => {
+            for (key, child) in map {
+                pointer.push('/');
+                pointer.push_str(&escape_token(key));
+                collect_paths(child, pred, pointer, paths);
+                pointer.truncate(len);
+            }
+        }
+        Value::List(list) => {
+            for (i, child) in list.iter().enumerate() {
+                pointer.push('/');
+                pointer.push_str(&i.to_string());
+                collect_paths(child, pred, pointer, paths);
+                pointer.truncate(len);
+            }
+        }
+        _ => {}
+    }
+}
+
+impl<'a> Value<'a> {
+    /// Looks up a value by JSON Pointer (RFC 6901) like `/tests/0/data`. The
+    /// empty pointer refers to the whole value, `~1` and `~0` in a token stand for
+    /// `/` and `~`
+    pub fn pointer(&self, pointer: &str) -> Option<&Value<'a>> {
+        let mut target = self;
+        for token in reference_tokens(pointer)? {
+            target = match target {
+                Value::Dict(map) => map.get(&token)?,
+                Value::List(list) => list.get(array_index(&token)?)?,
+                _ => return None,
+            };
+        }
+        Some(target)
+    }
+
+    /// Same as [`Value::pointer`] but returns a mutable reference
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Value<'a>> {
+        let mut target = self;
+        for token in reference_tokens(pointer)? {
+            target = match target {
+                Value::Dict(map) => map.get_mut(&token)?,
+                Value::List(list) => list.get_mut(array_index(&token)?)?,
+                _ => return None,
+            };
+        }
+        Some(target)
+    }
+
+    /// JSON Pointer of every node, the root included, for which `pred` holds, in
+    /// document order. Each of them can be handed to [`Value::pointer_mut`]
+    pub fn paths_matching<F: Fn(&Value<'_>) -> bool>(&self, pred: F) -> Vec<String> {
+        let mut paths = Vec::new();
+        collect_paths(self, &pred, &mut String::new(), &mut paths);
+        paths
+    }
+
+    /// Value at the first of `pointers` that resolves to something other than
+    /// `null`, like SQL's `COALESCE`. `None` if none of them do
+    pub fn coalesce(&self, pointers: &[&str]) -> Option<&Value<'a>> {
+        pointers
+            .iter()
+            .filter_map(|pointer| self.pointer(pointer))
+            .find(|value| !matches!(value, Value::Null))
+    }
+
+    /// Returns an owned copy of the tree with the value at `pointer` replaced by
+    /// `new_value`, leaving `self` untouched. The immutable counterpart of
+    /// assigning through [`Value::pointer_mut`], so the pointer must resolve
+    pub fn with_pointer_set(
+        &self,
+        pointer: &str,
+        new_value: OwnedValue,
+    ) -> Result<OwnedValue, PointerError> {
+        if reference_tokens(pointer).is_none() {
+            return Err(PointerError::InvalidSyntax(pointer.to_string()));
+        }
+        let mut copy = self.clone().into_owned();
+        match copy.pointer_mut(pointer) {
+            Some(target) => *target = new_value,
+            None => return Err(PointerError::NotFound(pointer.to_string())),
+        }
+        Ok(copy)
+    }
+
+    /// Generalizes [`Value::project`] to JSON Pointers, the allowlist counterpart
+    /// of [`Value::redact_paths`]: copies only the values at `pointers` along
+    /// with the objects and arrays leading to them. Arrays keep the selected
+    /// elements in order, so their indices can shift. Pointers that don't
+    /// resolve are ignored
+    #[doc(alias = "project_deep")]
+    pub fn retain_paths(&self, pointers: &[&str]) -> OwnedValue {
+        let paths: Vec<Vec<String>> = pointers
+            .iter()
+            .filter(|pointer| self.pointer(pointer).is_some())
+            .filter_map(|pointer| reference_tokens(pointer))
+            .collect();
+        let paths: Vec<&[String]> = paths.iter().map(Vec::as_slice).collect();
+        project_tokens(self, &paths)
+    }
+
+    /// Replaces the value at every pointer with `null`, pointers that don't
+    /// resolve are ignored. [`Value::retain_paths`] keeps them instead
+    pub fn redact_paths(&mut self, pointers: &[&str]) {
+        self.redact_paths_with(pointers, &Value::Null);
+    }
+
+    /// Same as [`Value::redact_paths`] but with a custom `replacement`, like
+    /// `Value::Str("***".into())`
+    pub fn redact_paths_with(&mut self, pointers: &[&str], replacement: &Value<'a>) {
+        for pointer in pointers {
+            if let Some(target) = self.pointer_mut(pointer) {
+                *target = replacement.clone();
+            }
+        }
+    }
+}
+
+#[test]
+fn test_pointer() {
+    let value = crate::parse_str(r#"{"a/b": {"m~n": [10, 20]}, "": 1, "list": [[0]]}"#).unwrap();
+    assert_eq!(value.pointer(""), Some(&value));
+    assert_eq!(value.pointer("/a~1b/m~0n/1"), Some(&Value::Number(20.0)));
+    assert_eq!(value.pointer("/"), Some(&Value::Number(1.0)));
+    assert_eq!(value.pointer("/list/0/0"), Some(&Value::Number(0.0)));
+    assert_eq!(value.pointer("/list/00"), None);
+    assert_eq!(value.pointer("/list/-"), None);
+    assert_eq!(value.pointer("/list/1"), None);
+    assert_eq!(value.pointer("list"), None);
+    assert_eq!(value.pointer("/missing/0"), None);
+}
+
+#[test]
+fn test_redact_paths() {
+    let mut value = crate::parse_str(crate::value::NESTED_STRUCTURES).unwrap();
+    value.redact_paths(&["/tests/0/data", "/tests/5/data", "/missing"]);
+    assert_eq!(value.pointer("/tests/0/data"), Some(&Value::Null));
+    assert_eq!(value.pointer("/tests/0/valid"), Some(&Value::Bool(true)));
+    assert_eq!(value.pointer("/tests/1/data"), Some(&Value::Number(15.0)));
+
+    value.redact_paths_with(&["/schema/type"], &Value::Str("***".into()));
+    assert_eq!(
+        value.pointer("/schema/type"),
+        Some(&Value::Str("***".into()))
+    );
+}
+
+#[test]
+fn test_retain_paths_in_arrays() {
+    let value = crate::parse_str(crate::value::NESTED_STRUCTURES).unwrap();
+    let projected = value.retain_paths(&["/schema/type", "/tests/1/data", "/tests/1/valid", "/x"]);
+    assert_eq!(
+        projected,
+        crate::parse_str(
+            r#"{"schema": {"type": "string"}, "tests": [{"data": 15, "valid": false}]}"#
+        )
+        .unwrap()
+    );
+    assert_eq!(value.retain_paths(&[""]), value);
+    assert_eq!(value.retain_paths(&[]), Value::new_object());
+}
+
+#[test]
+fn test_with_pointer_set() {
+    let value = crate::parse_str(crate::value::NESTED_STRUCTURES).unwrap();
+    let original = value.clone();
+    let updated = value
+        .with_pointer_set("/tests/1/data", Value::Str("x".into()))
+        .unwrap();
+    assert_eq!(value, original);
+    assert_eq!(
+        updated.pointer("/tests/1/data"),
+        Some(&Value::Str("x".into()))
+    );
+    assert_eq!(updated.pointer("/tests/0"), value.pointer("/tests/0"));
+
+    assert_eq!(value.with_pointer_set("", Value::Null), Ok(Value::Null));
+    assert_eq!(
+        value.with_pointer_set("/tests/2", Value::Null),
+        Err(PointerError::NotFound("/tests/2".to_string()))
+    );
+    assert_eq!(
+        value.with_pointer_set("tests", Value::Null),
+        Err(PointerError::InvalidSyntax("tests".to_string()))
+    );
+}
+
+#[test]
+fn test_coalesce() {
+    let value = crate::parse_str(r#"{"user": {"port": null}, "default": {"port": 80}}"#).unwrap();
+    assert_eq!(
+        value.coalesce(&["/user/port", "/env/port", "/default/port"]),
+        Some(&Value::Number(80.0))
+    );
+    assert_eq!(value.coalesce(&["/user/port", "/env/port"]), None);
+    assert_eq!(value.coalesce(&[]), None);
+}
+
+#[test]
+fn test_retain_paths() {
+    let value = crate::parse_str(crate::value::NESTED_STRUCTURES).unwrap();
+    assert_eq!(
+        value.retain_paths(&["/schema/type", "/description", "/tests/9"]),
+        crate::parse_str(
+            r#"{"description": "The test case description", "schema": {"type": "string"}}"#
+        )
+        .unwrap()
+    );
+}
+
+#[test]
+fn test_paths_matching() {
+    let value = crate::parse_str(crate::value::NESTED_STRUCTURES).unwrap();
+    assert_eq!(
+        value.paths_matching(|node| matches!(node, Value::Bool(_))),
+        ["/tests/0/valid", "/tests/1/valid"]
+    );
+    assert_eq!(
+        value.paths_matching(|node| matches!(node, Value::Dict(_)))[..2],
+        ["", "/schema"]
+    );
+
+    let value = crate::parse_str(r#"{"a/b": {"~": 1}}"#).unwrap();
+    let paths = value.paths_matching(|node| matches!(node, Value::Number(_)));
+    assert_eq!(paths, ["/a~1b/~0"]);
+    assert_eq!(value.pointer(&paths[0]), Some(&Value::Number(1.0)));
+}
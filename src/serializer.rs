@@ -0,0 +1,404 @@
+use crate::parser::Value;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+/// Comparator deciding the order in which object keys are written
+pub type KeyOrder<'o> = &'o dyn Fn(&str, &str) -> Ordering;
+
+/// What one level of indentation is made of in pretty output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentStyle {
+    Spaces(usize),
+    Tabs,
+}
+
+impl IndentStyle {
+    fn push(self, out: &mut String, level: usize) {
+        match self {
+            IndentStyle::Spaces(width) => out.extend(core::iter::repeat_n(' ', width * level)),
+            IndentStyle::Tabs => out.extend(core::iter::repeat_n('\t', level)),
+        }
+    }
+}
+
+/// Options controlling the json written by [`to_string_with`]
+pub struct SerializeOptions<'o> {
+    /// Order in which the keys of every object are written. `None` writes them in
+    /// insertion order
+    pub key_order: Option<KeyOrder<'o>>,
+    /// Also escape `<`, `>`, `&` and the line separators U+2028/U+2029 as `\uXXXX`,
+    /// so the output can be embedded in a html `<script>` tag
+    pub html_safe: bool,
+    /// Write every element and pair on its own line, indented by one level per
+    /// nesting level. `None` writes compact json
+    pub indent: Option<IndentStyle>,
+    /// Put a space after every `:` and `,` of compact output, keeping it on one
+    /// line: `{"a": 1, "b": [1, 2]}`. Pretty output always has the spaces after
+    /// colons, so this has no effect there
+    pub spaced: bool,
+    /// Write numbers whose magnitude is at least this, or below its reciprocal,
+    /// in exponent notation like `1e21`. `None` always writes fixed notation,
+    /// which is exact but makes `1e308` take 309 bytes. Both notations use the
+    /// shortest digits that parse back to the same `f64`, so no precision is lost
+    /// either way, the fixed form of a huge number just pads with zeros that
+    /// carry no information. `Some(1e21)` matches the threshold of JavaScript
+    pub max_fixed_magnitude: Option<f64>,
+    /// What ends every line of pretty output, `"\n"` by default. `"\r\n"` suits
+    /// files for Windows tools
+    pub newline: &'o str,
+}
+
+impl Default for SerializeOptions<'_> {
+    fn default() -> Self {
+        Self {
+            key_order: None,
+            html_safe: false,
+            indent: None,
+            spaced: false,
+            max_fixed_magnitude: None,
+            newline: "\n",
+        }
+    }
+}
+
+/// Serializes `value` as compact json, with no whitespace between tokens
+pub fn to_string(value: &Value<'_>) -> String {
+    let mut result = String::with_capacity(value.serialized_len_hint());
+    write_value(&mut result, value, &SerializeOptions::default(), 0);
+    result
+}
+
+/// Serializes only the subtree of `value` at the JSON Pointer `pointer` as
+/// compact json, `None` if the pointer doesn't resolve
+pub fn serialize_pointer(value: &Value<'_>, pointer: &str) -> Option<String> {
+    value.pointer(pointer).map(to_string)
+}
+
+/// Serializes `value` as json indented by two spaces per level
+pub fn to_string_pretty(value: &Value<'_>) -> String {
+    let options = SerializeOptions {
+        indent: Some(IndentStyle::Spaces(2)),
+        ..Default::default()
+    };
+    to_string_with(value, &options)
+}
+
+/// Serializes `value` according to `options`
+///
+/// Json has no representation for `NaN` and the infinities, so they are written
+/// as `null` (like `JSON.stringify` does)
+pub fn to_string_with(value: &Value<'_>, options: &SerializeOptions<'_>) -> String {
+    let mut result = String::new();
+    write_value(&mut result, value, options, 0);
+    result
+}
+
+/// Starts a line for an element at `level` in pretty output, the separating comma
+/// has already been written
+fn new_line(out: &mut String, options: &SerializeOptions<'_>, level: usize) {
+    if let Some(indent) = options.indent {
+        out.push_str(options.newline);
+        indent.push(out, level);
+    }
+}
+
+/// Writes the comma between two elements or pairs
+fn comma(out: &mut String, options: &SerializeOptions<'_>) {
+    out.push(',');
+    if options.spaced && options.indent.is_none() {
+        out.push(' ');
+    }
+}
+
+fn write_value(out: &mut String, value: &Value<'_>, options: &SerializeOptions<'_>, level: usize) {
+    match value {
+        Value::Dict(map) if map.is_empty() => out.push_str("{}"),
+        Value::List(list) if list.is_empty() => out.push_str("[]"),
+        Value::Dict(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            if let Some(key_order) = options.key_order {
+                entries.sort_by(|(a, _), (b, _)| key_order(a, b));
+            }
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    comma(out, options);
+                }
+                new_line(out, options, level + 1);
+                write_string(out, key, options);
+                out.push(':');
+                if options.indent.is_some() || options.spaced {
+                    out.push(' ');
+                }
+                write_value(out, value, options, level + 1);
+            }
+            new_line(out, options, level);
+            out.push('}');
+        }
+        Value::List(list) => {
+            out.push('[');
+            for (i, item) in list.iter().enumerate() {
+                if i > 0 {
+                    comma(out, options);
+                }
+                new_line(out, options, level + 1);
+                write_value(out, item, options, level + 1);
+            }
+            new_line(out, options, level);
+            out.push(']');
+        }
+        Value::Str(s) => write_string(out, s, options),
+        Value::Number(n) if n.is_finite() => match options.max_fixed_magnitude {
+            Some(limit) if *n != 0.0 && (n.abs() >= limit || n.abs() < 1.0 / limit) => {
+                out.push_str(&format!("{:e}", n))
+            }
+            _ => out.push_str(&n.to_string()),
+        },
+        Value::Number(_) | Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+    }
+}
+
+fn write_string(out: &mut String, s: &str, options: &SerializeOptions<'_>) {
+    out.push('"');
+    push_escaped(out, s, options.html_safe);
+    out.push('"');
+}
+
+/// Escapes quotes, backslashes and control characters so that the string can be
+/// printed inside double quotes
+pub(crate) fn escape_string(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    push_escaped(&mut result, s, false);
+    result
+}
+
+fn push_escaped(out: &mut String, s: &str, html_safe: bool) {
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            '<' | '>' | '&' | '\u{2028}' | '\u{2029}' if html_safe => {
+                out.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+}
+
+/// Length of `s` once escaped by [`push_escaped`], without the quotes
+fn escaped_len(s: &str) -> usize {
+    s.chars()
+        .map(|c| match c {
+            '"' | '\\' | '\n' | '\t' | '\r' => 2,
+            c if c.is_control() => 6,
+            c => c.len_utf8(),
+        })
+        .sum()
+}
+
+/// Counts the bytes written to it instead of storing them
+struct LenCounter(usize);
+
+impl core::fmt::Write for LenCounter {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+impl Value<'_> {
+    /// Length in bytes of the compact serialization written by [`to_string`],
+    /// used to pre-size output buffers. Unlike [`Value::byte_size`] it counts
+    /// escapes and formats numbers, so it's exact, but it takes a bit longer
+    pub fn serialized_len_hint(&self) -> usize {
+        match self {
+            Value::Dict(map) => {
+                1 + map.len().max(1)
+                    + map
+                        .iter()
+                        .map(|(key, value)| escaped_len(key) + 3 + value.serialized_len_hint())
+                        .sum::<usize>()
+            }
+            Value::List(list) => {
+                1 + list.len().max(1) + list.iter().map(Value::serialized_len_hint).sum::<usize>()
+            }
+            Value::Str(s) => escaped_len(s) + 2,
+            Value::Number(n) if n.is_finite() => {
+                let mut counter = LenCounter(0);
+                core::fmt::Write::write_fmt(&mut counter, format_args!("{}", n)).unwrap();
+                counter.0
+            }
+            Value::Number(_) | Value::Null => 4,
+            Value::Bool(b) => {
+                if *b {
+                    4
+                } else {
+                    5
+                }
+            }
+        }
+    }
+}
+
+/// Writes the value as compact json, see [`to_string`]
+impl core::fmt::Display for Value<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", to_string(self))
+    }
+}
+
+#[test]
+fn test_to_string() {
+    let value = crate::parse_str(r#"[1, 2.5, "a\"b\n", true, null, {"k": []}, {}]"#).unwrap();
+    assert_eq!(
+        to_string(&value),
+        r#"[1,2.5,"a\"b\n",true,null,{"k":[]},{}]"#
+    );
+    assert_eq!(value.to_string(), to_string(&value));
+    assert_eq!(to_string(&Value::Number(f64::NAN)), "null");
+
+    let nested = crate::parse_str(crate::value::NESTED_STRUCTURES).unwrap();
+    assert_eq!(crate::parse_str(&to_string(&nested)).unwrap(), nested);
+}
+
+#[test]
+fn test_serialized_len_hint() {
+    let value = crate::parse_str(
+        r#"[1, -0.5, 1e300, "tab\t\u0001é", false, null, {"k\"": [], "": {}}, [[]]]"#,
+    )
+    .unwrap();
+    let json = to_string(&value);
+    assert_eq!(value.serialized_len_hint(), json.len());
+    // the buffer was sized up front and never grew
+    assert_eq!(json.capacity(), json.len());
+
+    let nested = crate::parse_str(crate::value::NESTED_STRUCTURES).unwrap();
+    assert_eq!(nested.serialized_len_hint(), to_string(&nested).len());
+    assert_eq!(Value::Number(f64::NAN).serialized_len_hint(), 4);
+}
+
+#[test]
+fn test_key_order() {
+    let value =
+        crate::parse_str(r#"{"name": "x", "id": 1, "age": {"b": 2, "a": 1, "id": 0}}"#).unwrap();
+    let id_first = |a: &str, b: &str| (a != "id").cmp(&(b != "id")).then(a.cmp(b));
+    let options = SerializeOptions {
+        key_order: Some(&id_first),
+        ..Default::default()
+    };
+    assert_eq!(
+        to_string_with(&value, &options),
+        r#"{"id":1,"age":{"id":0,"a":1,"b":2},"name":"x"}"#
+    );
+}
+
+#[test]
+fn test_html_safe() {
+    let value = crate::parse_str("{\"x\": \"</script>&\u{2028}\"}").unwrap();
+    assert_eq!(to_string(&value), "{\"x\":\"</script>&\u{2028}\"}");
+    let options = SerializeOptions {
+        html_safe: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        to_string_with(&value, &options),
+        r#"{"x":"\u003c/script\u003e\u0026\u2028"}"#
+    );
+}
+
+#[test]
+fn test_spaced() {
+    let value = crate::parse_str(r#"{"a": 1, "b": {"c": [1, 2, []], "d": {}}}"#).unwrap();
+    let options = SerializeOptions {
+        spaced: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        to_string_with(&value, &options),
+        r#"{"a": 1, "b": {"c": [1, 2, []], "d": {}}}"#
+    );
+    let options = SerializeOptions {
+        spaced: true,
+        indent: Some(IndentStyle::Spaces(2)),
+        ..Default::default()
+    };
+    assert_eq!(to_string_with(&value, &options), to_string_pretty(&value));
+}
+
+#[test]
+fn test_max_fixed_magnitude() {
+    let value = crate::parse_str("[1e21, -1.5e300, 2.5e-8, 0, 123.5, 1e-22]").unwrap();
+    let json = to_string(&value);
+    assert!(json.starts_with("[1000000000000000000000,-15"), "{}", json);
+    assert!(json.contains(",0.000000025,0,123.5,"), "{}", json);
+
+    let options = SerializeOptions {
+        max_fixed_magnitude: Some(1e21),
+        ..Default::default()
+    };
+    let json = to_string_with(&value, &options);
+    assert_eq!(json, "[1e21,-1.5e300,0.000000025,0,123.5,1e-22]");
+    assert_eq!(crate::parse_str(&json).unwrap(), value);
+    assert_eq!(
+        to_string_with(&Value::Number(1e20), &options),
+        "100000000000000000000"
+    );
+}
+
+#[test]
+fn test_newline() {
+    let value = crate::parse_str(r#"{"a": [1], "b": {}}"#).unwrap();
+    let options = SerializeOptions {
+        indent: Some(IndentStyle::Spaces(2)),
+        newline: "\r\n",
+        ..Default::default()
+    };
+    let json = to_string_with(&value, &options);
+    assert_eq!(json, "{\r\n  \"a\": [\r\n    1\r\n  ],\r\n  \"b\": {}\r\n}");
+    assert_eq!(json.replace("\r\n", "\n"), to_string_pretty(&value));
+}
+
+#[test]
+fn test_indent() {
+    let value = crate::parse_str(r#"{"a": [1, {"b": null}], "c": {}, "d": []}"#).unwrap();
+    assert_eq!(
+        to_string_pretty(&value),
+        "{\n  \"a\": [\n    1,\n    {\n      \"b\": null\n    }\n  ],\n  \"c\": {},\n  \"d\": []\n}"
+    );
+
+    let options = SerializeOptions {
+        indent: Some(IndentStyle::Tabs),
+        ..Default::default()
+    };
+    assert_eq!(
+        to_string_with(&value, &options),
+        "{\n\t\"a\": [\n\t\t1,\n\t\t{\n\t\t\t\"b\": null\n\t\t}\n\t],\n\t\"c\": {},\n\t\"d\": []\n}"
+    );
+    let nested = crate::parse_str(crate::value::NESTED_STRUCTURES).unwrap();
+    assert_eq!(
+        crate::parse_str(&to_string_with(&nested, &options)).unwrap(),
+        nested
+    );
+}
+
+#[test]
+fn test_serialize_pointer() {
+    let value = crate::parse_str(crate::value::NESTED_STRUCTURES).unwrap();
+    assert_eq!(
+        serialize_pointer(&value, "/schema").as_deref(),
+        Some(r#"{"type":"string"}"#)
+    );
+    assert_eq!(
+        serialize_pointer(&value, "/tests/1/data").as_deref(),
+        Some("15")
+    );
+    assert_eq!(serialize_pointer(&value, "/tests/2"), None);
+    assert_eq!(serialize_pointer(&value, ""), Some(to_string(&value)));
+}
@@ -0,0 +1,66 @@
+/// SipHash-2-4 with a 128-bit output. Written out here since the standard library
+/// only exposes the 64-bit variant, and only through the unstable `DefaultHasher`
+/// algorithm which may change between releases
+pub(crate) fn siphash128(key: [u8; 16], data: &[u8]) -> [u8; 16] {
+    let k0 = u64::from_le_bytes(key[..8].try_into().unwrap());
+    let k1 = u64::from_le_bytes(key[8..].try_into().unwrap());
+    let mut v = [
+        k0 ^ 0x736f6d6570736575,
+        k1 ^ 0x646f72616e646f6d ^ 0xee,
+        k0 ^ 0x6c7967656e657261,
+        k1 ^ 0x7465646279746573,
+    ];
+
+    let mut blocks = data.chunks_exact(8);
+    for block in &mut blocks {
+        compress(&mut v, u64::from_le_bytes(block.try_into().unwrap()));
+    }
+    // the last block holds the remaining bytes and the length in its top byte
+    let mut last = [0u8; 8];
+    last[..blocks.remainder().len()].copy_from_slice(blocks.remainder());
+    last[7] = data.len() as u8;
+    compress(&mut v, u64::from_le_bytes(last));
+
+    let mut out = [0u8; 16];
+    v[2] ^= 0xee;
+    (0..4).for_each(|_| round(&mut v));
+    out[..8].copy_from_slice(&(v[0] ^ v[1] ^ v[2] ^ v[3]).to_le_bytes());
+    v[1] ^= 0xdd;
+    (0..4).for_each(|_| round(&mut v));
+    out[8..].copy_from_slice(&(v[0] ^ v[1] ^ v[2] ^ v[3]).to_le_bytes());
+    out
+}
+
+fn compress(v: &mut [u64; 4], m: u64) {
+    v[3] ^= m;
+    round(v);
+    round(v);
+    v[0] ^= m;
+}
+
+fn round(v: &mut [u64; 4]) {
+    v[0] = v[0].wrapping_add(v[1]);
+    v[1] = v[1].rotate_left(13) ^ v[0];
+    v[0] = v[0].rotate_left(32);
+    v[2] = v[2].wrapping_add(v[3]);
+    v[3] = v[3].rotate_left(16) ^ v[2];
+    v[0] = v[0].wrapping_add(v[3]);
+    v[3] = v[3].rotate_left(21) ^ v[0];
+    v[2] = v[2].wrapping_add(v[1]);
+    v[1] = v[1].rotate_left(17) ^ v[2];
+    v[2] = v[2].rotate_left(32);
+}
+
+#[test]
+fn test_siphash128() {
+    // from the test vectors of the reference implementation, which use the key
+    // 00 01 .. 0f and the messages 00 01 .. (n - 1)
+    let key: [u8; 16] = core::array::from_fn(|i| i as u8);
+    assert_eq!(
+        siphash128(key, &[]),
+        [
+            0xa3, 0x81, 0x7f, 0x04, 0xba, 0x25, 0xa8, 0xe6, 0x6d, 0xf6, 0x72, 0x14, 0xc7, 0x55,
+            0x02, 0x93
+        ]
+    );
+}
@@ -0,0 +1,172 @@
+use crate::error::JsonError;
+use crate::lexer::{Lexer, Span, Token};
+use crate::parser::{Parser, ParserOptions};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// A value annotated with the byte range of its source text, for tools like
+/// language servers that map positions in the source back to nodes. The span of
+/// an object or array covers its brackets and the span of a string its quotes
+#[derive(Debug, PartialEq, Clone)]
+pub struct SpannedValue {
+    pub kind: SpannedKind,
+    pub span: Span,
+}
+
+impl SpannedValue {
+    /// The innermost node whose span contains the byte `offset`, `None` if the
+    /// offset is outside this value. An offset inside a key gives the object
+    /// holding the key, since keys aren't nodes of their own
+    pub fn find_node_at_offset(&self, offset: usize) -> Option<&SpannedValue> {
+        if !self.span.contains(offset) {
+            return None;
+        }
+        let child = match &self.kind {
+            SpannedKind::Dict(entries) => entries
+                .iter()
+                .find_map(|entry| entry.value.find_node_at_offset(offset)),
+            SpannedKind::List(items) => items
+                .iter()
+                .find_map(|item| item.find_node_at_offset(offset)),
+            _ => None,
+        };
+        child.or(Some(self))
+    }
+}
+
+/// Same variants as [`crate::Value`], with the children spanned as well
+#[derive(Debug, PartialEq, Clone)]
+pub enum SpannedKind {
+    Dict(Vec<SpannedEntry>),
+    List(Vec<SpannedValue>),
+    Bool(bool),
+    Str(String),
+    Number(f64),
+    Null,
+}
+
+/// A pair of an object in source order. Duplicate keys are all kept, since each
+/// of them is a separate node in the source
+#[derive(Debug, PartialEq, Clone)]
+pub struct SpannedEntry {
+    pub key: String,
+    pub key_span: Span,
+    pub value: SpannedValue,
+}
+
+/// Parses `input` like [`crate::parse_str`] but keeps the span of every node
+pub fn parse_spanned(input: &str) -> Result<SpannedValue, JsonError> {
+    let mut lexer = Lexer::new(input);
+    lexer.lex()?;
+    let parser = Parser::from_lexer(lexer, ParserOptions::default());
+    parser.validate()?;
+    let mut current = 0;
+    Ok(build(parser.tokens(), parser.spans(), &mut current))
+}
+
+/// Builds the value starting at `current` and leaves `current` past its last
+/// token. The tokens must already be validated, so the grammar isn't checked
+fn build(tokens: &[Token], spans: &[Span], current: &mut usize) -> SpannedValue {
+    let start = spans[*current].start;
+    let kind = match &tokens[*current] {
+        Token::LeftBrace(_) => {
+            let mut entries = Vec::new();
+            *current += 1;
+            while let Token::Str(key, _) = &tokens[*current] {
+                let key_span = spans[*current];
+                // skip the key and the colon
+                *current += 2;
+                let value = build(tokens, spans, current);
+                entries.push(SpannedEntry {
+                    key: key.clone(),
+                    key_span,
+                    value,
+                });
+                if let Token::Comma(_) = tokens[*current] {
+                    *current += 1;
+                }
+            }
+            SpannedKind::Dict(entries)
+        }
+        Token::LeftBracket(_) => {
+            let mut items = Vec::new();
+            *current += 1;
+            while !matches!(tokens[*current], Token::RightBracket(_)) {
+                items.push(build(tokens, spans, current));
+                if let Token::Comma(_) = tokens[*current] {
+                    *current += 1;
+                }
+            }
+            SpannedKind::List(items)
+        }
+        Token::Str(s, _) => SpannedKind::Str(s.clone()),
+        Token::Number(n, _) => SpannedKind::Number(*n),
+        Token::Bool(b, _) => SpannedKind::Bool(*b),
+        Token::Null(_) => SpannedKind::Null,
+        token => unreachable!("validated tokens can't start a value with {}", token),
+    };
+    // the closing bracket of a container or the scalar token itself
+    let end = spans[*current].end;
+    *current += 1;
+    SpannedValue {
+        kind,
+        span: Span { start, end },
+    }
+}
+
+#[test]
+fn test_parse_spanned() {
+    let source = crate::value::NESTED_STRUCTURES;
+    let root = parse_spanned(source).unwrap();
+    assert_eq!(&source[root.span.start..root.span.end], source.trim());
+
+    let SpannedKind::Dict(entries) = &root.kind else {
+        panic!("expected a dict")
+    };
+    let schema = &entries[1];
+    assert_eq!(schema.key, "schema");
+    assert_eq!(
+        &source[schema.key_span.start..schema.key_span.end],
+        "\"schema\""
+    );
+    assert_eq!(
+        &source[schema.value.span.start..schema.value.span.end],
+        r#"{ "type": "string" }"#
+    );
+
+    let SpannedKind::List(tests) = &entries[2].value.kind else {
+        panic!("expected a list")
+    };
+    let SpannedKind::Dict(fields) = &tests[1].kind else {
+        panic!("expected a dict")
+    };
+    assert_eq!(fields[1].value.kind, SpannedKind::Number(15.0));
+    assert_eq!(
+        &source[fields[1].value.span.start..fields[1].value.span.end],
+        "15"
+    );
+
+    assert!(matches!(parse_spanned("[1, 2,]"), Err(JsonError::Parse(_))));
+}
+
+#[test]
+fn test_find_node_at_offset() {
+    let source = crate::value::NESTED_STRUCTURES;
+    let root = parse_spanned(source).unwrap();
+
+    let offset = source.find("invalid instance").unwrap();
+    let node = root.find_node_at_offset(offset).unwrap();
+    assert_eq!(
+        node.kind,
+        SpannedKind::Str("a test with an invalid instance".to_string())
+    );
+
+    // between two values of the array, so the array itself
+    let offset = source.rfind("},").unwrap() + 1;
+    let node = root.find_node_at_offset(offset).unwrap();
+    assert!(matches!(&node.kind, SpannedKind::List(items) if items.len() == 2));
+
+    let offset = source.find("\"schema\"").unwrap();
+    assert_eq!(root.find_node_at_offset(offset), Some(&root));
+    assert_eq!(root.find_node_at_offset(0), None);
+}
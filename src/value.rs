@@ -0,0 +1,1740 @@
+use crate::convert::{FromValue, FromValueError};
+use crate::error::JsonError;
+use crate::map::Map;
+use crate::parser::{OwnedValue, Value};
+use crate::pointer::array_index;
+use crate::serializer::{SerializeOptions, to_string_with};
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::hash::Hash;
+#[cfg(feature = "std")]
+use std::hash::{DefaultHasher, Hasher};
+#[cfg(feature = "std")]
+use std::path::Path;
+
+/// Source of randomness for [`Value::sample`], kept minimal so the crate doesn't
+/// depend on an rng crate. Any generator can be wrapped by forwarding `next_u64`
+pub trait Rng {
+    fn next_u64(&mut self) -> u64;
+}
+
+/// Names one of the variants of [`Value`] without its contents
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TypeTag {
+    Object,
+    Array,
+    String,
+    Number,
+    Bool,
+    Null,
+}
+
+/// A set of json types as bit flags, combined with `|`. A validator can check
+/// whether a value has one of the allowed types with
+/// `allowed.contains(value.type_tag())`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct TypeSet(u8);
+
+impl TypeSet {
+    pub const EMPTY: TypeSet = TypeSet(0);
+    pub const OBJECT: TypeSet = TypeSet(1);
+    pub const ARRAY: TypeSet = TypeSet(1 << 1);
+    pub const STRING: TypeSet = TypeSet(1 << 2);
+    pub const NUMBER: TypeSet = TypeSet(1 << 3);
+    pub const BOOL: TypeSet = TypeSet(1 << 4);
+    pub const NULL: TypeSet = TypeSet(1 << 5);
+    pub const ALL: TypeSet = TypeSet(0b11_1111);
+
+    /// Whether every type in `other` is also in `self`
+    pub fn contains(self, other: TypeSet) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl core::ops::BitOr for TypeSet {
+    type Output = TypeSet;
+
+    fn bitor(self, other: TypeSet) -> TypeSet {
+        TypeSet(self.0 | other.0)
+    }
+}
+
+impl From<TypeTag> for TypeSet {
+    fn from(tag: TypeTag) -> Self {
+        match tag {
+            TypeTag::Object => TypeSet::OBJECT,
+            TypeTag::Array => TypeSet::ARRAY,
+            TypeTag::String => TypeSet::STRING,
+            TypeTag::Number => TypeSet::NUMBER,
+            TypeTag::Bool => TypeSet::BOOL,
+            TypeTag::Null => TypeSet::NULL,
+        }
+    }
+}
+
+impl TypeTag {
+    /// The tag of the variant of `value`
+    pub fn of(value: &Value<'_>) -> TypeTag {
+        match value {
+            Value::Dict(_) => TypeTag::Object,
+            Value::List(_) => TypeTag::Array,
+            Value::Str(_) => TypeTag::String,
+            Value::Number(_) => TypeTag::Number,
+            Value::Bool(_) => TypeTag::Bool,
+            Value::Null => TypeTag::Null,
+        }
+    }
+}
+
+impl core::fmt::Display for TypeTag {
+    /// Writes the same name as [`Value::type_name`]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let name = match self {
+            TypeTag::Object => "object",
+            TypeTag::Array => "array",
+            TypeTag::String => "string",
+            TypeTag::Number => "number",
+            TypeTag::Bool => "bool",
+            TypeTag::Null => "null",
+        };
+        f.write_str(name)
+    }
+}
+
+/// What [`Value::rename_key_with`] does when the new key is already taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyConflict {
+    /// Drop the existing pair, the renamed one takes its place
+    Overwrite,
+    /// Leave the object untouched and report that nothing was renamed
+    Skip,
+}
+
+/// Order of scalars for [`Value::deep_sort`]: `null`, then bools, numbers and
+/// strings, each compared by value. Numbers use `f64::total_cmp`
+fn scalar_cmp(a: &Value<'_>, b: &Value<'_>) -> core::cmp::Ordering {
+    fn rank(value: &Value<'_>) -> u8 {
+        match value {
+            Value::Null => 0,
+            Value::Bool(_) => 1,
+            Value::Number(_) => 2,
+            Value::Str(_) => 3,
+            Value::List(_) => 4,
+            Value::Dict(_) => 5,
+        }
+    }
+
+    match (a, b) {
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        (Value::Number(a), Value::Number(b)) => a.total_cmp(b),
+        (Value::Str(a), Value::Str(b)) => a.cmp(b),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+impl<'a> Value<'a> {
+    /// Name of the json type of the value, used in error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Dict(_) => "object",
+            Value::List(_) => "array",
+            Value::Str(_) => "string",
+            Value::Number(_) => "number",
+            Value::Bool(_) => "bool",
+            Value::Null => "null",
+        }
+    }
+
+    /// Converts every element of an array with `T::from_value`, stopping at the
+    /// first element that fails and reporting its index
+    pub fn as_array_of<T: FromValue>(&self) -> Result<Vec<T>, FromValueError> {
+        match self {
+            Value::List(list) => list
+                .iter()
+                .enumerate()
+                .map(|(index, item)| {
+                    T::from_value(item).map_err(|error| FromValueError::AtIndex {
+                        index,
+                        error: Box::new(error),
+                    })
+                })
+                .collect(),
+            other => Err(FromValueError::TypeMismatch {
+                expected: "array",
+                found: other.type_name(),
+            }),
+        }
+    }
+
+    /// Copies every borrowed string and key, so the result no longer depends on
+    /// the tokens it was parsed from
+    pub fn into_owned(self) -> OwnedValue {
+        match self {
+            Value::Dict(map) => {
+                Value::Dict(Map::from_entries(map.into_iter().map(|(key, value)| {
+                    (Cow::Owned(key.into_owned()), value.into_owned())
+                })))
+            }
+            Value::List(list) => Value::List(list.into_iter().map(Value::into_owned).collect()),
+            Value::Str(s) => Value::Str(Cow::Owned(s.into_owned())),
+            Value::Number(n) => Value::Number(n),
+            Value::Bool(b) => Value::Bool(b),
+            Value::Null => Value::Null,
+        }
+    }
+
+    /// Builds a new tree with `f` applied to every string value. Keys are copied
+    /// unchanged, see [`Value::map_keys_and_strings`] to transform them too
+    pub fn map_strings<F: FnMut(&str) -> String>(&self, mut f: F) -> OwnedValue {
+        self._map_strings(&mut f, false)
+    }
+
+    /// Same as [`Value::map_strings`] but `f` is also applied to every key
+    pub fn map_keys_and_strings<F: FnMut(&str) -> String>(&self, mut f: F) -> OwnedValue {
+        self._map_strings(&mut f, true)
+    }
+
+    fn _map_strings<F: FnMut(&str) -> String>(&self, f: &mut F, keys: bool) -> OwnedValue {
+        match self {
+            Value::Dict(map) => Value::Dict(Map::from_entries(map.iter().map(|(key, value)| {
+                let key = if keys { f(key) } else { key.to_string() };
+                (Cow::Owned(key), value._map_strings(f, keys))
+            }))),
+            Value::List(list) => {
+                Value::List(list.iter().map(|item| item._map_strings(f, keys)).collect())
+            }
+            Value::Str(s) => Value::Str(Cow::Owned(f(s))),
+            other => other.clone().into_owned(),
+        }
+    }
+
+    /// Removes duplicate elements from every array in the tree, keeping the first
+    /// occurrence. Nested arrays are deduplicated before their parent, so two
+    /// elements that become equal are also merged
+    ///
+    /// Elements are compared with `==`, so numbers use exact `f64` equality:
+    /// `0.0` and `-0.0` are duplicates, while `NaN` never equals anything and every
+    /// `NaN` is kept
+    pub fn dedup_arrays(&mut self) {
+        match self {
+            Value::Dict(map) => map.values_mut().for_each(Value::dedup_arrays),
+            Value::List(list) => {
+                let mut unique: Vec<Value<'a>> = Vec::with_capacity(list.len());
+                for mut item in list.drain(..) {
+                    item.dedup_arrays();
+                    if !unique.contains(&item) {
+                        unique.push(item);
+                    }
+                }
+                *list = unique;
+            }
+            _ => {}
+        }
+    }
+
+    /// Number of keys summed over every object in the tree
+    pub fn total_keys(&self) -> usize {
+        match self {
+            Value::Dict(map) => map.len() + map.values().map(Value::total_keys).sum::<usize>(),
+            Value::List(list) => list.iter().map(Value::total_keys).sum(),
+            _ => 0,
+        }
+    }
+
+    /// Number of values in the tree, containers and scalars alike, counting the
+    /// root. Keys aren't values, so they don't count
+    pub fn node_count(&self) -> usize {
+        1 + match self {
+            Value::Dict(map) => map.values().map(Value::node_count).sum(),
+            Value::List(list) => list.iter().map(Value::node_count).sum(),
+            _ => 0,
+        }
+    }
+
+    /// Length of the longest array in the tree, `0` if there are no arrays
+    pub fn max_array_length(&self) -> usize {
+        match self {
+            Value::Dict(map) => map.values().map(Value::max_array_length).max().unwrap_or(0),
+            Value::List(list) => list
+                .iter()
+                .map(Value::max_array_length)
+                .max()
+                .unwrap_or(0)
+                .max(list.len()),
+            _ => 0,
+        }
+    }
+
+    /// The pairs of an object in the order they appear in the source, `None` if
+    /// the value isn't an object
+    pub fn ordered_entries(&self) -> Option<&[(Cow<'a, str>, Value<'a>)]> {
+        match self {
+            Value::Dict(map) => Some(map.as_slice()),
+            _ => None,
+        }
+    }
+
+    /// Hashes the structure of the tree: the key names and the types of the values
+    /// but not the contents of strings, numbers and bools. Objects hash the same
+    /// regardless of key order, and arrays only hash the distinct shapes of their
+    /// elements, so their length and order don't matter either
+    ///
+    /// The hash is only stable within one build of the crate. Needs the `std`
+    /// feature for its hasher
+    #[cfg(feature = "std")]
+    pub fn shape_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.type_name().hash(&mut hasher);
+        match self {
+            Value::Dict(map) => {
+                // summing makes the result independent of the order of the keys
+                let pairs = map.iter().fold(0u64, |sum, (key, value)| {
+                    let mut pair_hasher = DefaultHasher::new();
+                    key.hash(&mut pair_hasher);
+                    value.shape_hash().hash(&mut pair_hasher);
+                    sum.wrapping_add(pair_hasher.finish())
+                });
+                pairs.hash(&mut hasher);
+            }
+            Value::List(list) => {
+                let mut shapes: Vec<u64> = list.iter().map(Value::shape_hash).collect();
+                shapes.sort_unstable();
+                shapes.dedup();
+                shapes.hash(&mut hasher);
+            }
+            _ => {}
+        }
+        hasher.finish()
+    }
+
+    /// An empty object
+    pub fn new_object() -> Self {
+        Value::Dict(Map::new())
+    }
+
+    /// An empty array
+    pub fn new_array() -> Self {
+        Value::List(Vec::new())
+    }
+
+    /// An object with `pairs` in the given order, borrowing the keys. A repeated
+    /// key replaces the value of the earlier one like [`Map::insert`]
+    pub fn object_from_pairs(pairs: Vec<(&'a str, Value<'a>)>) -> Self {
+        Value::Dict(
+            pairs
+                .into_iter()
+                .map(|(key, value)| (Cow::Borrowed(key), value))
+                .collect(),
+        )
+    }
+
+    /// An object with the keys of `map` written with their `Display` impl, for
+    /// converting Rust maps whose keys aren't strings. Any map works, the pairs
+    /// are inserted in its iteration order, which for a `HashMap` is arbitrary
+    pub fn object_from_display_map<K, V, M>(map: M) -> OwnedValue
+    where
+        K: core::fmt::Display,
+        V: Into<OwnedValue>,
+        M: IntoIterator<Item = (K, V)>,
+    {
+        Value::Dict(
+            map.into_iter()
+                .map(|(key, value)| (Cow::Owned(key.to_string()), value.into()))
+                .collect(),
+        )
+    }
+
+    /// An array of `items`
+    pub fn array_from(items: Vec<Value<'a>>) -> Self {
+        Value::List(items)
+    }
+
+    /// Returns the value for `key` in an object, inserting the result of `f` first
+    /// if the key is missing. Chaining calls builds nested objects:
+    /// `root.get_mut_or_insert_with("a", Value::new_object).get_mut_or_insert_with("b", ...)`
+    ///
+    /// Panics if the value isn't an object
+    pub fn get_mut_or_insert_with(
+        &mut self,
+        key: &str,
+        f: impl FnOnce() -> Value<'a>,
+    ) -> &mut Value<'a> {
+        match self {
+            Value::Dict(map) => map.get_mut_or_insert_with(key, f),
+            other => panic!("get_mut_or_insert_with called on {}", other.type_name()),
+        }
+    }
+
+    /// Looks up `key` in an object ignoring ASCII case, so `"NAME"` finds a
+    /// `"name"` key. If several keys differ only by case the first one in
+    /// insertion order wins. `None` for non-objects
+    pub fn get_ci(&self, key: &str) -> Option<&Value<'a>> {
+        match self {
+            Value::Dict(map) => map
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Value of the first of `keys` present in an object, for fields that go by
+    /// several names like `["color", "colour"]`. `None` for non-objects
+    pub fn get_first_of(&self, keys: &[&str]) -> Option<&Value<'a>> {
+        match self {
+            Value::Dict(map) => keys.iter().find_map(|key| map.get(key)),
+            _ => None,
+        }
+    }
+
+    /// Rewrites every number to a canonical form so that documents differing only
+    /// in how their numbers are written compare and serialize the same:
+    /// - `-0.0` becomes `0.0`.
+    /// - Nothing else needs to be done since numbers are already stored as `f64`,
+    ///   so `1.50`, `1.5` and `15e-1` parse to the same value.
+    /// - `NaN` and the infinities (which can't come from json text) are left as is.
+    pub fn normalize_numbers(&mut self) {
+        match self {
+            Value::Dict(map) => map.values_mut().for_each(Value::normalize_numbers),
+            Value::List(list) => list.iter_mut().for_each(Value::normalize_numbers),
+            // `-0.0 == 0.0`, so this also matches negative zero
+            Value::Number(n) if *n == 0.0 => *n = 0.0,
+            _ => {}
+        }
+    }
+
+    /// Number of nested container levels, `0` for scalars and `1` for a flat
+    /// array or object
+    pub fn depth(&self) -> usize {
+        match self {
+            Value::Dict(map) => 1 + map.values().map(Value::depth).max().unwrap_or(0),
+            Value::List(list) => 1 + list.iter().map(Value::depth).max().unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    /// Picks a random subtree whose depth is at most `max_depth`, by walking down
+    /// through randomly chosen children that still lead to such a subtree. Once
+    /// the current node is shallow enough the walk stops there or goes further
+    /// down with equal chance. `None` if every subtree is deeper, like `[[]]`
+    /// with `max_depth` 0. The same `rng` state always gives the same result
+    pub fn sample(&self, rng: &mut impl Rng, max_depth: usize) -> Option<OwnedValue> {
+        // the depth of the shallowest subtree, an empty container is one itself
+        fn shallowest(value: &Value) -> usize {
+            match value {
+                Value::Dict(map) if !map.is_empty() => map.values().map(shallowest).min().unwrap(),
+                Value::List(list) if !list.is_empty() => list.iter().map(shallowest).min().unwrap(),
+                _ => value.depth(),
+            }
+        }
+
+        if shallowest(self) > max_depth {
+            return None;
+        }
+        let mut node = self;
+        loop {
+            let children: Vec<&Value> = match node {
+                Value::Dict(map) => map.values().collect(),
+                Value::List(list) => list.iter().collect(),
+                _ => Vec::new(),
+            }
+            .into_iter()
+            .filter(|child| shallowest(child) <= max_depth)
+            .collect();
+            // a node that is too deep always has a child leading further down
+            if node.depth() <= max_depth && (children.is_empty() || rng.next_u64() & 1 == 0) {
+                return Some(node.clone().into_owned());
+            }
+            node = children[(rng.next_u64() % children.len() as u64) as usize];
+        }
+    }
+
+    /// Estimates the length of the compact serialization in one pass without
+    /// allocating. Strings are counted without escapes and non-integral numbers
+    /// are assumed to take 8 bytes, so the estimate is exact for documents with
+    /// integral numbers and no characters that need escaping
+    pub fn byte_size(&self) -> usize {
+        // brackets plus one separator between every two items
+        fn punctuation(items: usize) -> usize {
+            2 + items.saturating_sub(1)
+        }
+
+        match self {
+            Value::Dict(map) => {
+                punctuation(map.len())
+                    + map
+                        .iter()
+                        .map(|(key, value)| key.len() + 3 + value.byte_size())
+                        .sum::<usize>()
+            }
+            Value::List(list) => {
+                punctuation(list.len()) + list.iter().map(Value::byte_size).sum::<usize>()
+            }
+            Value::Str(s) => s.len() + 2,
+            Value::Number(n) if n % 1.0 == 0.0 && n.abs() < 1e16 => {
+                let mut digits = 1;
+                let mut rest = n.abs();
+                while rest >= 10.0 {
+                    rest /= 10.0;
+                    digits += 1;
+                }
+                digits + usize::from(n.is_sign_negative())
+            }
+            Value::Number(_) => 8,
+            Value::Bool(true) | Value::Null => 4,
+            Value::Bool(false) => 5,
+        }
+    }
+
+    /// Smooths over fields that may hold either one value or a list of them:
+    /// the elements of an array, a single element for any other value and
+    /// nothing for `null`
+    pub fn ensure_array(&self) -> Vec<&Value<'a>> {
+        match self {
+            Value::List(list) => list.iter().collect(),
+            Value::Null => Vec::new(),
+            other => vec![other],
+        }
+    }
+
+    /// A new object with only the top-level pairs whose key is in `keys`, in the
+    /// order of the source. Keys that aren't present are skipped, and a value
+    /// that isn't an object gives an empty object. See [`Value::retain_paths`]
+    /// for nested keys
+    pub fn project(&self, keys: &[&str]) -> OwnedValue {
+        let Value::Dict(map) = self else {
+            return Value::new_object();
+        };
+        Value::Dict(Map::from_entries(
+            map.iter()
+                .filter(|(key, _)| keys.contains(&key.as_ref()))
+                .map(|(key, value)| (Cow::Owned(key.to_string()), value.clone().into_owned())),
+        ))
+    }
+
+    /// Renames the key `from` of an object to `to`, keeping its position, and
+    /// returns whether it happened. An existing pair with key `to` is replaced,
+    /// see [`Value::rename_key_with`] to keep it instead. Values that aren't
+    /// objects are left as they are
+    pub fn rename_key(&mut self, from: &str, to: &str) -> bool {
+        self.rename_key_with(from, to, KeyConflict::Overwrite)
+    }
+
+    /// Same as [`Value::rename_key`] with `conflict` deciding what happens when
+    /// `to` is already a key
+    pub fn rename_key_with(&mut self, from: &str, to: &str, conflict: KeyConflict) -> bool {
+        match self {
+            Value::Dict(map) => {
+                if conflict == KeyConflict::Skip && from != to && map.contains_key(to) {
+                    return false;
+                }
+                map.rename(from, Cow::Owned(to.to_string()))
+            }
+            _ => false,
+        }
+    }
+
+    /// Renames `from` to `to` in every object of the tree and returns how many
+    /// objects were changed. Children are renamed before their parent
+    pub fn rename_key_deep(&mut self, from: &str, to: &str, conflict: KeyConflict) -> usize {
+        let renamed = match self {
+            Value::Dict(map) => map
+                .values_mut()
+                .map(|value| value.rename_key_deep(from, to, conflict))
+                .sum(),
+            Value::List(list) => list
+                .iter_mut()
+                .map(|item| item.rename_key_deep(from, to, conflict))
+                .sum(),
+            _ => 0,
+        };
+        renamed + usize::from(self.rename_key_with(from, to, conflict))
+    }
+
+    /// Moves the pairs of an object out in order, so a transform can build a new
+    /// tree without cloning. Nothing is yielded for other values. Strings that
+    /// are already owned are moved, borrowed ones are copied
+    pub fn into_entries(self) -> impl Iterator<Item = (String, OwnedValue)> {
+        let map = match self {
+            Value::Dict(map) => map,
+            _ => Map::new(),
+        };
+        map.into_iter()
+            .map(|(key, value)| (key.into_owned(), value.into_owned()))
+    }
+
+    /// Partial match for test assertions: every pair of an object in `self` must
+    /// be in the matching object of `other` with a value that is itself a subset,
+    /// arrays must have the same length with each element a subset of the one at
+    /// the same index, and scalars must be equal. Extra keys in `other` are fine
+    pub fn is_subset_of(&self, other: &Value<'_>) -> bool {
+        match (self, other) {
+            (Value::Dict(expected), Value::Dict(actual)) => expected.iter().all(|(key, value)| {
+                actual
+                    .get(key)
+                    .is_some_and(|actual| value.is_subset_of(actual))
+            }),
+            (Value::List(expected), Value::List(actual)) => {
+                expected.len() == actual.len()
+                    && expected
+                        .iter()
+                        .zip(actual)
+                        .all(|(expected, actual)| expected.is_subset_of(actual))
+            }
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Null, Value::Null) => true,
+            _ => false,
+        }
+    }
+
+    /// Same as `==` except that two numbers are equal when they're at most
+    /// `epsilon` apart, for comparing documents that went through float
+    /// arithmetic. Like `==`, the order of object keys doesn't matter
+    pub fn approx_eq(&self, other: &Value<'_>, epsilon: f64) -> bool {
+        match (self, other) {
+            (Value::Dict(a), Value::Dict(b)) => {
+                a.len() == b.len()
+                    && a.iter().all(|(key, value)| {
+                        b.get(key)
+                            .is_some_and(|other| value.approx_eq(other, epsilon))
+                    })
+            }
+            (Value::List(a), Value::List(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.approx_eq(b, epsilon))
+            }
+            // `==` first so that equal infinities match
+            (Value::Number(a), Value::Number(b)) => a == b || (a - b).abs() <= epsilon,
+            (a, b) => a == b,
+        }
+    }
+
+    /// Fills in the keys of `defaults` that are missing from `self`, going into
+    /// objects present in both. Values already in `self` always win, including
+    /// ones of a different type than the default, and arrays aren't merged
+    pub fn apply_defaults(&mut self, defaults: &Value<'_>) {
+        let (Value::Dict(map), Value::Dict(defaults)) = (self, defaults) else {
+            return;
+        };
+        for (key, default) in defaults {
+            match map.get_mut(key) {
+                Some(value) => value.apply_defaults(default),
+                None => {
+                    map.insert(Cow::Owned(key.to_string()), default.clone().into_owned());
+                }
+            }
+        }
+    }
+
+    /// Copies the tree with every object or array that would make it deeper than
+    /// `max` replaced by the string `"...(truncated)"`, so the result has a
+    /// [`Value::depth`] of at most `max`. Meant for logging untrusted input
+    pub fn clamp_depth(&self, max: usize) -> OwnedValue {
+        match self {
+            Value::Dict(_) | Value::List(_) if max == 0 => {
+                Value::Str(Cow::Borrowed("...(truncated)"))
+            }
+            Value::Dict(map) => {
+                Value::Dict(Map::from_entries(map.iter().map(|(key, value)| {
+                    (Cow::Owned(key.to_string()), value.clamp_depth(max - 1))
+                })))
+            }
+            Value::List(list) => {
+                Value::List(list.iter().map(|item| item.clamp_depth(max - 1)).collect())
+            }
+            other => other.clone().into_owned(),
+        }
+    }
+
+    /// Element `i` of an array, `None` if it's out of range or the value isn't
+    /// an array
+    pub fn at(&self, i: usize) -> Option<&Value<'a>> {
+        match self {
+            Value::List(list) => list.get(i),
+            _ => None,
+        }
+    }
+
+    /// Like [`Value::at`] but also gives the value of the `i`th pair of an
+    /// object in source order, which is mostly useful for debugging
+    pub fn nth(&self, i: usize) -> Option<&Value<'a>> {
+        match self {
+            Value::Dict(map) => map.values().nth(i),
+            other => other.at(i),
+        }
+    }
+
+    /// Number of nodes at every level of the tree, starting with the value itself
+    /// at level 0. Wide documents have a few large buckets, deep ones many small
+    /// buckets
+    pub fn depth_histogram(&self) -> Vec<usize> {
+        let mut histogram = Vec::new();
+        let mut level = vec![self];
+        while !level.is_empty() {
+            histogram.push(level.len());
+            let mut next = Vec::new();
+            for node in level {
+                match node {
+                    Value::Dict(map) => next.extend(map.values()),
+                    Value::List(list) => next.extend(list),
+                    _ => {}
+                }
+            }
+            level = next;
+        }
+        histogram
+    }
+
+    /// Strips leading and trailing ASCII whitespace from every string value, keys
+    /// are left alone. Borrowed strings are narrowed without copying
+    pub fn trim_string_values(&mut self) {
+        match self {
+            Value::Dict(map) => map.values_mut().for_each(Value::trim_string_values),
+            Value::List(list) => list.iter_mut().for_each(Value::trim_string_values),
+            Value::Str(Cow::Borrowed(s)) => *s = s.trim_ascii(),
+            Value::Str(Cow::Owned(s)) => {
+                let end = s.trim_ascii_end().len();
+                s.truncate(end);
+                let start = s.len() - s.trim_ascii_start().len();
+                s.drain(..start);
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether the value is of the variant named by `kind`
+    pub fn is_type(&self, kind: TypeTag) -> bool {
+        matches!(
+            (self, kind),
+            (Value::Dict(_), TypeTag::Object)
+                | (Value::List(_), TypeTag::Array)
+                | (Value::Str(_), TypeTag::String)
+                | (Value::Number(_), TypeTag::Number)
+                | (Value::Bool(_), TypeTag::Bool)
+                | (Value::Null, TypeTag::Null)
+        )
+    }
+
+    /// Every value of type `kind` anywhere in the tree, the value itself
+    /// included, in document order with parents before their children
+    pub fn values_of_type(&self, kind: TypeTag) -> Vec<&Value<'a>> {
+        let mut found = Vec::new();
+        self._values_of_type(kind, &mut found);
+        found
+    }
+
+    fn _values_of_type<'v>(&'v self, kind: TypeTag, found: &mut Vec<&'v Value<'a>>) {
+        if self.is_type(kind) {
+            found.push(self);
+        }
+        match self {
+            Value::Dict(map) => map
+                .values()
+                .for_each(|value| value._values_of_type(kind, found)),
+            Value::List(list) => list
+                .iter()
+                .for_each(|item| item._values_of_type(kind, found)),
+            _ => {}
+        }
+    }
+
+    /// A 128-bit SipHash of the canonical form of the value: keys sorted and
+    /// numbers normalized, see [`Value::normalize_numbers`]. Unlike
+    /// [`Value::shape_hash`] the result doesn't depend on the build or the run,
+    /// so it can be stored, e.g. as a content address
+    pub fn stable_hash(&self) -> [u8; 16] {
+        // any fixed key works, it only has to stay the same forever
+        crate::siphash::siphash128([0; 16], &self.canonical_json_bytes())
+    }
+
+    /// The canonical form of the value as UTF-8 bytes, for signing. Documents
+    /// without duplicate keys that compare equal give the same bytes. Duplicate
+    /// keys kept by [`Map::append`] stay in their original relative order, so
+    /// `{"a":1,"a":2}` and `{"a":2,"a":1}` compare equal but differ here:
+    /// - keys are sorted by their UTF-8 bytes, at every level
+    /// - there is no whitespace between tokens
+    /// - integral numbers have no fraction (`1.0` is `1`), `-0` is `0`, other
+    ///   numbers use the shortest digits that round trip, never an exponent, and
+    ///   `NaN` and the infinities are `null`
+    /// - strings only escape `"`, `\` and control characters, as `\n`, `\r`,
+    ///   `\t` or lowercase `\u00xx`, everything else is written as is
+    ///
+    /// This is close to but not the same as RFC 8785, which sorts keys by UTF-16
+    /// code units and formats numbers like JavaScript
+    pub fn canonical_json_bytes(&self) -> Vec<u8> {
+        let mut canonical = self.clone();
+        canonical.normalize_numbers();
+        let key_order = |a: &str, b: &str| a.cmp(b);
+        let options = SerializeOptions {
+            key_order: Some(&key_order),
+            ..Default::default()
+        };
+        to_string_with(&canonical, &options).into_bytes()
+    }
+
+    /// The type of the value as a [`TypeSet`] with exactly one member
+    pub fn type_tag(&self) -> TypeSet {
+        match self {
+            Value::Dict(_) => TypeSet::OBJECT,
+            Value::List(_) => TypeSet::ARRAY,
+            Value::Str(_) => TypeSet::STRING,
+            Value::Number(_) => TypeSet::NUMBER,
+            Value::Bool(_) => TypeSet::BOOL,
+            Value::Null => TypeSet::NULL,
+        }
+    }
+
+    /// Shortens every string value longer than `max_len` chars to its first
+    /// `max_len` chars followed by `…`. Lengths are counted in chars, so a
+    /// multibyte character is never split. Keys are left alone
+    pub fn truncate_strings(&mut self, max_len: usize) {
+        match self {
+            Value::Dict(map) => map
+                .values_mut()
+                .for_each(|value| value.truncate_strings(max_len)),
+            Value::List(list) => list
+                .iter_mut()
+                .for_each(|item| item.truncate_strings(max_len)),
+            Value::Str(s) => {
+                if let Some((end, _)) = s.char_indices().nth(max_len) {
+                    let mut truncated = s[..end].to_string();
+                    truncated.push('…');
+                    *s = Cow::Owned(truncated);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The number as an `i64` when it's integral and in range, `None` for
+    /// fractions, numbers beyond the `i64` range and other types. Same rules as
+    /// the [`FromValue`] impl of `i64`
+    pub fn as_integer(&self) -> Option<i64> {
+        i64::from_value(self).ok()
+    }
+
+    /// Sorts the keys of every object and the elements of every array holding
+    /// only scalars, so documents that differ only in the order of keys and of
+    /// such arrays become equal element by element. Arrays containing objects or
+    /// arrays keep their order, see [`Value::deep_sort_all`]
+    pub fn deep_sort(&mut self) {
+        self._deep_sort(false);
+    }
+
+    /// Same as [`Value::deep_sort`] but every array is sorted, elements that
+    /// aren't all scalars are ordered by their serialization after sorting them
+    pub fn deep_sort_all(&mut self) {
+        self._deep_sort(true);
+    }
+
+    fn _deep_sort(&mut self, all: bool) {
+        match self {
+            Value::Dict(map) => {
+                map.values_mut().for_each(|value| value._deep_sort(all));
+                map.sort_keys();
+            }
+            Value::List(list) => {
+                list.iter_mut().for_each(|item| item._deep_sort(all));
+                if list
+                    .iter()
+                    .all(|item| !matches!(item, Value::Dict(_) | Value::List(_)))
+                {
+                    list.sort_by(scalar_cmp);
+                } else if all {
+                    list.sort_by_cached_key(|item| item.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Builds a new tree by offering every node to `f`, starting at the root. When
+    /// `f` returns a replacement the node is replaced and its children aren't
+    /// visited, otherwise the node is copied with `f` applied to its children
+    pub fn deep_map<F: FnMut(&Value<'a>) -> Option<OwnedValue>>(&self, mut f: F) -> OwnedValue {
+        self._deep_map(&mut f)
+    }
+
+    fn _deep_map<F: FnMut(&Value<'a>) -> Option<OwnedValue>>(&self, f: &mut F) -> OwnedValue {
+        if let Some(replacement) = f(self) {
+            return replacement;
+        }
+        match self {
+            Value::Dict(map) => {
+                Value::Dict(Map::from_entries(map.iter().map(|(key, value)| {
+                    (Cow::Owned(key.to_string()), value._deep_map(f))
+                })))
+            }
+            Value::List(list) => Value::List(list.iter().map(|item| item._deep_map(f)).collect()),
+            other => other.clone().into_owned(),
+        }
+    }
+
+    /// Sets the value at a dotted path like `a.b.c`, as given by command line
+    /// overrides (`--set a.b.c=5`). A numeric segment indexes an array that is
+    /// already there, padding it with `null` up to the index. Every other segment
+    /// names a key, creating the object when it's missing and replacing whatever
+    /// non-object value was in its place
+    pub fn set_path(&mut self, dotted: &str, value: OwnedValue) {
+        let mut target = self;
+        for segment in dotted.split('.') {
+            let index = array_index(segment).filter(|_| matches!(target, Value::List(_)));
+            target = match (target, index) {
+                (Value::List(list), Some(i)) => {
+                    if i >= list.len() {
+                        list.resize(i + 1, Value::Null);
+                    }
+                    &mut list[i]
+                }
+                (target, _) => {
+                    if !matches!(target, Value::Dict(_)) {
+                        *target = Value::new_object();
+                    }
+                    target.get_mut_or_insert_with(segment, Value::new_object)
+                }
+            };
+        }
+        *target = value;
+    }
+
+    /// Pairs up the elements of two arrays of the same length, so `[1, 2]` and
+    /// `["a", "b"]` give `[[1, "a"], [2, "b"]]`. `None` if either isn't an array
+    /// or their lengths differ
+    pub fn zip_arrays(a: &Value<'_>, b: &Value<'_>) -> Option<OwnedValue> {
+        match (a, b) {
+            (Value::List(a), Value::List(b)) if a.len() == b.len() => Some(Value::List(
+                a.iter()
+                    .zip(b)
+                    .map(|(a, b)| Value::List(vec![a.clone().into_owned(), b.clone().into_owned()]))
+                    .collect(),
+            )),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl OwnedValue {
+    /// Reads the file at `path` and parses its contents. The tree is owned since
+    /// the contents are dropped before returning
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<OwnedValue, JsonError> {
+        let source = std::fs::read_to_string(path)?;
+        crate::parse_str(&source)
+    }
+}
+
+impl<'a> TryFrom<&'a str> for Value<'a> {
+    type Error = JsonError;
+
+    /// Same as [`crate::parse_str`], the strings of the result are always owned
+    fn try_from(input: &'a str) -> Result<Self, Self::Error> {
+        crate::parse_str(input)
+    }
+}
+
+impl From<bool> for Value<'_> {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<f64> for Value<'_> {
+    fn from(n: f64) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl From<String> for Value<'_> {
+    fn from(s: String) -> Self {
+        Value::Str(Cow::Owned(s))
+    }
+}
+
+/// Consumes the value the way [`Value::ensure_array`] borrows it: the elements
+/// of an array, the value itself once for anything else (objects included) and
+/// nothing for `null`
+impl<'a> IntoIterator for Value<'a> {
+    type Item = Value<'a>;
+    type IntoIter = alloc::vec::IntoIter<Value<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self {
+            Value::List(list) => list.into_iter(),
+            Value::Null => Vec::new().into_iter(),
+            other => vec![other].into_iter(),
+        }
+    }
+}
+
+/// Collects pairs into an object, a repeated key keeps the last value like
+/// [`Map::insert`]
+impl<'a> FromIterator<(&'a str, Value<'a>)> for Value<'a> {
+    fn from_iter<I: IntoIterator<Item = (&'a str, Value<'a>)>>(iter: I) -> Self {
+        Value::Dict(
+            iter.into_iter()
+                .map(|(key, value)| (Cow::Borrowed(key), value))
+                .collect(),
+        )
+    }
+}
+
+/// Collects values into an array
+impl<'a> FromIterator<Value<'a>> for Value<'a> {
+    fn from_iter<I: IntoIterator<Item = Value<'a>>>(iter: I) -> Self {
+        Value::List(iter.into_iter().collect())
+    }
+}
+
+/// The document used by `test_nested_structures` in the parser
+#[cfg(test)]
+pub(crate) const NESTED_STRUCTURES: &str = r#"
+    {
+        "description": "The test case description",
+        "schema": { "type": "string" },
+        "tests": [
+            {
+                "description": "a test with a valid instance",
+                "data": "a string",
+                "valid": true
+            },
+            {
+                "description": "a test with an invalid instance",
+                "data": 15,
+                "valid": false
+            }
+        ]
+    }
+    "#;
+
+#[cfg(feature = "std")]
+#[test]
+fn test_from_file() {
+    let path =
+        std::env::temp_dir().join(format!("json_parser_from_file_{}.json", std::process::id()));
+    std::fs::write(&path, r#"{"name": "Alice", "scores": [95.5, 88.0]}"#).unwrap();
+    let value = Value::from_file(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    let mut expected = crate::map::Map::new();
+    expected.insert("name".into(), Value::Str("Alice".into()));
+    expected.insert(
+        "scores".into(),
+        Value::List(vec![Value::Number(95.5), Value::Number(88.0)]),
+    );
+    assert_eq!(value.unwrap(), Value::Dict(expected));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_from_file_errors() {
+    let path = std::env::temp_dir().join(format!(
+        "json_parser_from_file_errors_{}.json",
+        std::process::id()
+    ));
+    assert!(matches!(Value::from_file(&path), Err(JsonError::Io(_))));
+
+    std::fs::write(&path, "[1, 2").unwrap();
+    let value = Value::from_file(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(value, Err(JsonError::Parse(_))));
+}
+
+#[test]
+fn test_as_array_of() {
+    let value = crate::parse_str(r#"{"name": "Alice", "scores": [95.5, 88.0, 76]}"#).unwrap();
+    let Value::Dict(map) = &value else {
+        panic!("expected a dict")
+    };
+    assert_eq!(
+        map["scores"].as_array_of::<f64>(),
+        Ok(vec![95.5, 88.0, 76.0])
+    );
+    assert_eq!(
+        map["name"].as_array_of::<f64>(),
+        Err(FromValueError::TypeMismatch {
+            expected: "array",
+            found: "string"
+        })
+    );
+
+    let mixed = crate::parse_str(r#"[1, 2, "three", false]"#).unwrap();
+    assert_eq!(
+        mixed.as_array_of::<i64>(),
+        Err(FromValueError::AtIndex {
+            index: 2,
+            error: Box::new(FromValueError::TypeMismatch {
+                expected: "number",
+                found: "string"
+            })
+        })
+    );
+}
+
+#[test]
+fn test_map_strings() {
+    let value = crate::parse_str(NESTED_STRUCTURES).unwrap();
+    let upper = value.map_strings(|s| s.to_uppercase());
+    let expected = crate::parse_str(
+        r#"
+        {
+            "description": "THE TEST CASE DESCRIPTION",
+            "schema": { "type": "STRING" },
+            "tests": [
+                {
+                    "description": "A TEST WITH A VALID INSTANCE",
+                    "data": "A STRING",
+                    "valid": true
+                },
+                {
+                    "description": "A TEST WITH AN INVALID INSTANCE",
+                    "data": 15,
+                    "valid": false
+                }
+            ]
+        }
+        "#,
+    )
+    .unwrap();
+    assert_eq!(upper, expected);
+
+    let keys = crate::parse_str(r#"{"a": ["b"]}"#)
+        .unwrap()
+        .map_keys_and_strings(|s| s.repeat(2));
+    assert_eq!(keys, crate::parse_str(r#"{"aa": ["bb"]}"#).unwrap());
+}
+
+#[test]
+fn test_dedup_arrays() {
+    let mut value =
+        crate::parse_str(r#"{"a": {"b": [1, 1, 2, 3, 3]}, "c": [[1, 1], [1], "x", "x"]}"#).unwrap();
+    value.dedup_arrays();
+    assert_eq!(
+        value,
+        crate::parse_str(r#"{"a": {"b": [1, 2, 3]}, "c": [[1], "x"]}"#).unwrap()
+    );
+
+    let mut nan = Value::List(vec![Value::Number(f64::NAN), Value::Number(f64::NAN)]);
+    nan.dedup_arrays();
+    assert!(matches!(&nan, Value::List(list) if list.len() == 2));
+}
+
+#[test]
+fn test_document_metrics() {
+    let value = crate::parse_str(NESTED_STRUCTURES).unwrap();
+    assert_eq!(value.total_keys(), 10);
+    assert_eq!(value.max_array_length(), 2);
+
+    let value = crate::parse_str(r#"[1, [1, 2, 3, 4], {"a": []}]"#).unwrap();
+    assert_eq!(value.total_keys(), 1);
+    assert_eq!(value.max_array_length(), 4);
+}
+
+#[test]
+fn test_ordered_entries() {
+    let value =
+        crate::parse_str(r#"{"zebra": 1, "apple": [], "mango": {"b": 2, "a": 3}}"#).unwrap();
+    let entries = value.ordered_entries().unwrap();
+    let keys: Vec<&str> = entries.iter().map(|(key, _)| key.as_ref()).collect();
+    assert_eq!(keys, ["zebra", "apple", "mango"]);
+    assert_eq!(entries[0].1, Value::Number(1.0));
+
+    let nested = entries[2].1.ordered_entries().unwrap();
+    assert_eq!(nested[0].0, "b");
+    assert_eq!(nested[1].0, "a");
+    assert_eq!(Value::Null.ordered_entries(), None);
+}
+
+#[test]
+fn test_try_from() {
+    let value = Value::try_from(r#"{"a": [1, "b"]}"#).unwrap();
+    assert_eq!(value.to_string(), r#"{"a":[1,"b"]}"#);
+
+    let error = Value::try_from("[1, 2").unwrap_err();
+    assert!(matches!(error, JsonError::Parse(_)));
+    let error = Value::try_from("[1, @]").unwrap_err();
+    assert!(matches!(error, JsonError::Lex(_)));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_shape_hash() {
+    let a = crate::parse_str(NESTED_STRUCTURES).unwrap();
+    let b = crate::parse_str(
+        r#"
+        {
+            "tests": [
+                {"valid": false, "data": 1, "description": "numbers first"},
+                {"valid": true, "data": "other", "description": "then strings"},
+                {"valid": true, "data": "more", "description": "and again"}
+            ],
+            "schema": {"type": "number"},
+            "description": "Another description"
+        }
+        "#,
+    )
+    .unwrap();
+    assert_eq!(a.shape_hash(), b.shape_hash());
+
+    let c = crate::parse_str(r#"{"description": "x", "schema": {"kind": "string"}, "tests": []}"#)
+        .unwrap();
+    assert_ne!(a.shape_hash(), c.shape_hash());
+    assert_ne!(
+        Value::Number(1.0).shape_hash(),
+        Value::Str("1".into()).shape_hash()
+    );
+}
+
+#[test]
+fn test_get_mut_or_insert_with() {
+    let mut root = Value::new_object();
+    *root
+        .get_mut_or_insert_with("a", Value::new_object)
+        .get_mut_or_insert_with("b", Value::new_object)
+        .get_mut_or_insert_with("c", || Value::Null) = Value::Number(1.0);
+    root.get_mut_or_insert_with("a", Value::new_object)
+        .get_mut_or_insert_with("b", Value::new_object)
+        .get_mut_or_insert_with("d", Value::new_array);
+    // existing keys are returned without calling `f`
+    root.get_mut_or_insert_with("a", || unreachable!());
+
+    assert_eq!(
+        root,
+        crate::parse_str(r#"{"a": {"b": {"c": 1, "d": []}}}"#).unwrap()
+    );
+}
+
+#[test]
+fn test_normalize_numbers() {
+    let mut value = crate::parse_str(r#"{"a": [-0.0, 1.50, -0], "b": {"c": -0e5}}"#).unwrap();
+    assert_eq!(value.to_string(), r#"{"a":[-0,1.5,-0],"b":{"c":-0}}"#);
+    value.normalize_numbers();
+    assert_eq!(value.to_string(), r#"{"a":[0,1.5,0],"b":{"c":0}}"#);
+}
+
+#[test]
+fn test_sample() {
+    // xorshift64, enough for a reproducible sequence
+    struct XorShift(u64);
+    impl Rng for XorShift {
+        fn next_u64(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+    }
+
+    let value = crate::parse_str(NESTED_STRUCTURES).unwrap();
+    assert_eq!(value.depth(), 3);
+    for seed in 1..50 {
+        let sample = value.sample(&mut XorShift(seed), 1).unwrap();
+        assert!(sample.depth() <= 1);
+        assert_eq!(Some(sample), value.sample(&mut XorShift(seed), 1));
+        assert_eq!(value.sample(&mut XorShift(seed), 0).unwrap().depth(), 0);
+    }
+    assert!(value.sample(&mut XorShift(1), 3).unwrap().depth() <= 3);
+
+    // empty containers have depth 1, so there is nothing to pick at depth 0
+    let value = crate::parse_str("[[], {}]").unwrap();
+    assert_eq!(value.sample(&mut XorShift(1), 0), None);
+    assert_eq!(value.sample(&mut XorShift(1), 1).unwrap().depth(), 1);
+}
+
+#[test]
+fn test_byte_size() {
+    let value = crate::parse_str(NESTED_STRUCTURES).unwrap();
+    assert_eq!(value.byte_size(), value.to_string().len());
+
+    let value = crate::parse_str(r#"[-120, 0, [], {}, {"pi": 3.14159, "e": 2.718}]"#).unwrap();
+    let actual = value.to_string().len();
+    assert!(value.byte_size().abs_diff(actual) <= actual / 5);
+}
+
+#[test]
+fn test_ensure_array() {
+    let value =
+        crate::parse_str(r#"{"single": "a.rs", "many": ["b.rs", "c.rs"], "none": null}"#).unwrap();
+    let Value::Dict(map) = &value else {
+        panic!("expected a dict")
+    };
+    let names = |key: &str| -> Vec<String> {
+        map[key]
+            .ensure_array()
+            .into_iter()
+            .map(|item| String::from_value(item).unwrap())
+            .collect()
+    };
+    assert_eq!(names("single"), ["a.rs"]);
+    assert_eq!(names("many"), ["b.rs", "c.rs"]);
+    assert!(names("none").is_empty());
+}
+
+#[test]
+fn test_project() {
+    let value = crate::parse_str(NESTED_STRUCTURES).unwrap();
+    let projected = value.project(&["description", "tests", "missing"]);
+    let Value::Dict(map) = &projected else {
+        panic!("expected a dict")
+    };
+    let keys: Vec<&str> = map.keys().map(|key| key.as_ref()).collect();
+    assert_eq!(keys, ["description", "tests"]);
+    assert_eq!(projected.pointer("/tests"), value.pointer("/tests"));
+    assert_eq!(Value::Null.project(&["a"]), Value::new_object());
+}
+
+#[test]
+fn test_rename_key() {
+    let mut value = crate::parse_str(NESTED_STRUCTURES).unwrap();
+    assert!(
+        value
+            .pointer_mut("/schema")
+            .unwrap()
+            .rename_key("type", "kind")
+    );
+    assert_eq!(value.pointer("/schema/type"), None);
+    assert_eq!(
+        value.pointer("/schema/kind"),
+        Some(&Value::Str("string".into()))
+    );
+    assert!(!value.rename_key("missing", "other"));
+
+    assert!(!value.rename_key_with("schema", "tests", KeyConflict::Skip));
+    assert_eq!(value.rename_key_deep("data", "valid", KeyConflict::Skip), 0);
+    assert_eq!(
+        value.rename_key_deep("description", "name", KeyConflict::Overwrite),
+        3
+    );
+    assert_eq!(
+        value.pointer("/tests/1/name"),
+        Some(&Value::Str("a test with an invalid instance".into()))
+    );
+
+    assert!(value.rename_key("schema", "tests"));
+    assert_eq!(
+        value.pointer("/tests/kind"),
+        Some(&Value::Str("string".into()))
+    );
+}
+
+#[test]
+fn test_into_entries() {
+    let value = crate::parse_str(NESTED_STRUCTURES).unwrap();
+    let entries: Vec<(String, OwnedValue)> = value.clone().into_entries().collect();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].0, "description");
+
+    let rebuilt = Value::Dict(
+        entries
+            .into_iter()
+            .map(|(key, value)| (Cow::Owned(key), value))
+            .collect(),
+    );
+    assert_eq!(rebuilt, value);
+    assert_eq!(Value::Null.into_entries().count(), 0);
+}
+
+#[test]
+fn test_is_subset_of() {
+    let actual = crate::parse_str(NESTED_STRUCTURES).unwrap();
+    let expected = crate::parse_str(
+        r#"{"schema": {}, "tests": [{"valid": true}, {"data": 15, "valid": false}]}"#,
+    )
+    .unwrap();
+    assert!(expected.is_subset_of(&actual));
+    assert!(actual.is_subset_of(&actual));
+    assert!(!actual.is_subset_of(&expected));
+
+    for expected in [
+        r#"{"tests": [{"valid": false}, {}]}"#,
+        r#"{"tests": [{}]}"#,
+        r#"{"schema": {"type": "number"}}"#,
+        r#"{"missing": null}"#,
+    ] {
+        let expected = crate::parse_str(expected).unwrap();
+        assert!(!expected.is_subset_of(&actual), "{}", expected);
+    }
+}
+
+#[test]
+fn test_apply_defaults() {
+    let mut config =
+        crate::parse_str(r#"{"server": {"port": 9000}, "log": "debug", "features": ["a"]}"#)
+            .unwrap();
+    let defaults = crate::parse_str(
+        r#"
+        {
+            "server": {"host": "localhost", "port": 8080},
+            "log": {"level": "info"},
+            "features": ["x", "y"],
+            "retries": 3
+        }
+        "#,
+    )
+    .unwrap();
+    config.apply_defaults(&defaults);
+    assert_eq!(
+        config,
+        crate::parse_str(
+            r#"
+            {
+                "server": {"port": 9000, "host": "localhost"},
+                "log": "debug",
+                "features": ["a"],
+                "retries": 3
+            }
+            "#
+        )
+        .unwrap()
+    );
+}
+
+#[test]
+fn test_clamp_depth() {
+    let value = crate::parse_str(NESTED_STRUCTURES).unwrap();
+    let clamped = value.clamp_depth(1);
+    assert_eq!(clamped.depth(), 1);
+    assert_eq!(
+        clamped.pointer("/tests"),
+        Some(&Value::Str("...(truncated)".into()))
+    );
+    assert_eq!(
+        clamped.pointer("/description"),
+        value.pointer("/description")
+    );
+
+    let clamped = value.clamp_depth(2);
+    assert_eq!(clamped.pointer("/schema"), value.pointer("/schema"));
+    assert_eq!(
+        clamped.pointer("/tests/0"),
+        Some(&Value::Str("...(truncated)".into()))
+    );
+    assert_eq!(value.clamp_depth(3), value);
+}
+
+#[test]
+fn test_at_and_nth() {
+    let value = crate::parse_str(NESTED_STRUCTURES).unwrap();
+    let tests = value.pointer("/tests").unwrap();
+    assert_eq!(tests.at(1), value.pointer("/tests/1"));
+    assert_eq!(tests.at(2), None);
+    assert_eq!(value.at(0), None);
+
+    assert_eq!(value.nth(1), value.pointer("/schema"));
+    assert_eq!(tests.nth(0), tests.at(0));
+    assert_eq!(value.nth(3), None);
+}
+
+#[test]
+fn test_depth_histogram() {
+    let value = crate::parse_str(NESTED_STRUCTURES).unwrap();
+    assert_eq!(value.depth_histogram(), [1, 3, 3, 6]);
+    assert_eq!(Value::Null.depth_histogram(), [1]);
+    assert_eq!(
+        crate::parse_str("[[], [[1, 2]]]")
+            .unwrap()
+            .depth_histogram(),
+        [1, 2, 1, 2]
+    );
+}
+
+#[test]
+fn test_trim_string_values() {
+    let mut value =
+        crate::parse_str("{\" key \": [\"  hello  \", {\"a\": \"\\tb\\n\"}], \"c\": \"   \"}")
+            .unwrap();
+    value.trim_string_values();
+    assert_eq!(
+        value,
+        crate::parse_str(r#"{" key ": ["hello", {"a": "b"}], "c": ""}"#).unwrap()
+    );
+
+    let mut borrowed = Value::List(vec![Value::Str(" x ".into()), Value::Number(1.0)]);
+    borrowed.trim_string_values();
+    assert_eq!(borrowed.at(0), Some(&Value::Str("x".into())));
+}
+
+#[test]
+fn test_values_of_type() {
+    let value = crate::parse_str(r#"{"a": [1, {"b": 2.5}], "c": "3", "d": -4}"#).unwrap();
+    let numbers: Vec<f64> = value
+        .values_of_type(TypeTag::Number)
+        .into_iter()
+        .map(|n| f64::from_value(n).unwrap())
+        .collect();
+    assert_eq!(numbers, [1.0, 2.5, -4.0]);
+
+    let value = crate::parse_str(NESTED_STRUCTURES).unwrap();
+    assert_eq!(
+        value.values_of_type(TypeTag::Number),
+        [&Value::Number(15.0)]
+    );
+    assert_eq!(value.values_of_type(TypeTag::String).len(), 5);
+    assert_eq!(value.values_of_type(TypeTag::Object).len(), 4);
+    assert!(value.values_of_type(TypeTag::Null).is_empty());
+}
+
+#[test]
+fn test_stable_hash() {
+    let a = crate::parse_str(r#"{"b": [1, {"y": -0, "x": "s"}], "a": null}"#).unwrap();
+    let b = crate::parse_str(r#"{"a": null, "b": [1.0, {"x": "s", "y": 0}]}"#).unwrap();
+    assert_eq!(a.stable_hash(), b.stable_hash());
+
+    let c = crate::parse_str(r#"{"a": null, "b": [{"x": "s", "y": 0}, 1]}"#).unwrap();
+    assert_ne!(a.stable_hash(), c.stable_hash());
+    assert_ne!(
+        Value::Str("1".into()).stable_hash(),
+        Value::Number(1.0).stable_hash()
+    );
+}
+
+#[test]
+fn test_type_set() {
+    let allowed = TypeSet::STRING | TypeSet::NUMBER;
+    assert!(allowed.contains(Value::Number(1.0).type_tag()));
+    assert!(allowed.contains(Value::Str("a".into()).type_tag()));
+    assert!(!allowed.contains(Value::Bool(true).type_tag()));
+    assert!(!allowed.contains(TypeSet::STRING | TypeSet::NULL));
+
+    assert_eq!(TypeSet::from(TypeTag::Array), Value::new_array().type_tag());
+    assert!(TypeSet::ALL.contains(Value::Null.type_tag()));
+    assert!(TypeSet::EMPTY.is_empty());
+    assert!(!TypeSet::EMPTY.contains(TypeSet::OBJECT));
+}
+
+#[test]
+fn test_truncate_strings() {
+    let mut value = crate::parse_str(
+        r#"{"title": "héllo wörld", "tags": ["日本語のテキスト", "short"], "n": 12345}"#,
+    )
+    .unwrap();
+    value.truncate_strings(5);
+    assert_eq!(
+        value,
+        crate::parse_str(r#"{"title": "héllo…", "tags": ["日本語のテ…", "short"], "n": 12345}"#)
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_object_from_pairs() {
+    let schema = Value::object_from_pairs(vec![("type", Value::Str("string".into()))]);
+    let value = crate::parse_str(NESTED_STRUCTURES).unwrap();
+    assert_eq!(value.pointer("/schema"), Some(&schema));
+
+    let built = Value::object_from_pairs(vec![
+        (
+            "b",
+            Value::array_from(vec![Value::Number(1.0), Value::Null]),
+        ),
+        ("a", Value::object_from_pairs(vec![])),
+        ("b", Value::array_from(vec![Value::Bool(true)])),
+    ]);
+    assert_eq!(built.to_string(), r#"{"b":[true],"a":{}}"#);
+}
+
+#[test]
+fn test_as_integer() {
+    let value = crate::parse_str("[3.0, 3.5, -7, 1e19, -9223372036854775808, \"3\"]").unwrap();
+    let integers: Vec<Option<i64>> = (0..6).map(|i| value.at(i).unwrap().as_integer()).collect();
+    assert_eq!(
+        integers,
+        [Some(3), None, Some(-7), None, Some(i64::MIN), None]
+    );
+}
+
+#[test]
+fn test_deep_sort() {
+    let mut value = crate::parse_str(
+        r#"{"z": [3, "b", null, 1, true, "a"], "a": {"y": [{"k": 2}, {"k": 1}], "x": []}}"#,
+    )
+    .unwrap();
+    value.deep_sort();
+    assert_eq!(
+        value.to_string(),
+        r#"{"a":{"x":[],"y":[{"k":2},{"k":1}]},"z":[null,true,1,3,"a","b"]}"#
+    );
+
+    let mut twin = crate::parse_str(
+        r#"{"a": {"x": [], "y": [{"k": 1}, {"k": 2}]}, "z": ["a", true, 1, "b", 3, null]}"#,
+    )
+    .unwrap();
+    twin.deep_sort_all();
+    value.deep_sort_all();
+    assert_eq!(value.to_string(), twin.to_string());
+}
+
+#[test]
+fn test_deep_map() {
+    let value = crate::parse_str(
+        r#"{"a": "null", "b": ["x", "null", {"null": "null"}], "c": {"d": "keep"}}"#,
+    )
+    .unwrap();
+    let mapped = value.deep_map(|node| match node {
+        Value::Str(s) if s == "null" => Some(Value::Null),
+        _ => None,
+    });
+    assert_eq!(
+        mapped,
+        crate::parse_str(r#"{"a": null, "b": ["x", null, {"null": null}], "c": {"d": "keep"}}"#)
+            .unwrap()
+    );
+
+    // a replaced node isn't visited any further
+    let mut visited = 0;
+    let mapped = value.deep_map(|node| {
+        visited += 1;
+        matches!(node, Value::List(_)).then(Value::new_array)
+    });
+    assert_eq!(mapped.pointer("/b"), Some(&Value::new_array()));
+    assert_eq!(visited, 5);
+}
+
+#[test]
+fn test_get_ci() {
+    let value = crate::parse_str(r#"{"name": "a", "Name": "b", "id": 1}"#).unwrap();
+    assert_eq!(value.get_ci("NAME"), Some(&Value::Str("a".into())));
+    assert_eq!(value.get_ci("Id"), Some(&Value::Number(1.0)));
+    assert_eq!(value.get_ci("nam"), None);
+    assert_eq!(Value::Null.get_ci("name"), None);
+}
+
+#[test]
+fn test_from_iterator() {
+    let value: Value = [("a", Value::Number(1.0)), ("b", Value::Null)]
+        .into_iter()
+        .collect();
+    assert_eq!(value, crate::parse_str(r#"{"a": 1, "b": null}"#).unwrap());
+
+    let value: Value = (1..=3).map(|n| Value::Number(n as f64)).collect();
+    assert_eq!(value, crate::parse_str("[1, 2, 3]").unwrap());
+
+    let empty: Value = core::iter::empty::<Value>().collect();
+    assert_eq!(empty, Value::new_array());
+}
+
+#[test]
+fn test_node_count() {
+    let value = crate::parse_str(NESTED_STRUCTURES).unwrap();
+    assert_eq!(value.node_count(), 13);
+    assert_eq!(Value::Null.node_count(), 1);
+    assert_eq!(crate::parse_str("[[], {}]").unwrap().node_count(), 3);
+}
+
+#[test]
+fn test_approx_eq() {
+    let a = crate::parse_str(r#"{"x": [0.3, {"y": 1}], "s": "a"}"#).unwrap();
+    let b = crate::parse_str(r#"{"s": "a", "x": [0.300000000001, {"y": 1}]}"#).unwrap();
+    assert_ne!(a, b);
+    assert!(a.approx_eq(&b, 1e-9));
+    assert!(!a.approx_eq(&b, 1e-13));
+    assert!(Value::Number(0.1 + 0.2).approx_eq(&Value::Number(0.3), f64::EPSILON));
+
+    let c = crate::parse_str(r#"{"x": [0.3, {"y": "1"}], "s": "a"}"#).unwrap();
+    assert!(!a.approx_eq(&c, 1.0));
+    assert!(Value::Number(f64::INFINITY).approx_eq(&Value::Number(f64::INFINITY), 0.0));
+}
+
+#[test]
+fn test_object_from_display_map() {
+    let map: std::collections::HashMap<u32, bool> = [(1, true), (20, false)].into();
+    assert_eq!(
+        Value::object_from_display_map(map),
+        crate::parse_str(r#"{"1": true, "20": false}"#).unwrap()
+    );
+
+    let map = std::collections::BTreeMap::from([('a', 1.5), ('b', 2.0)]);
+    let value = Value::object_from_display_map(map);
+    assert_eq!(value.to_string(), r#"{"a":1.5,"b":2}"#);
+}
+
+#[test]
+fn test_into_iter() {
+    let value = crate::parse_str(r#"[1, "a", {"b": null}]"#).unwrap();
+    let items: Vec<OwnedValue> = value.into_iter().collect();
+    assert_eq!(items.len(), 3);
+    assert_eq!(items[1], Value::Str("a".into()));
+
+    let mut count = 0;
+    for item in crate::parse_str(r#"{"a": 1}"#).unwrap() {
+        assert!(matches!(item, Value::Dict(_)));
+        count += 1;
+    }
+    assert_eq!(count, 1);
+    assert_eq!(Value::Null.into_iter().count(), 0);
+}
+
+#[test]
+fn test_get_first_of() {
+    let value = crate::parse_str(r#"{"colour": "red", "shade": null}"#).unwrap();
+    assert_eq!(
+        value.get_first_of(&["color", "colour"]),
+        Some(&Value::Str("red".into()))
+    );
+    // a present null still counts
+    assert_eq!(value.get_first_of(&["shade", "colour"]), Some(&Value::Null));
+    assert_eq!(value.get_first_of(&["hue"]), None);
+    assert_eq!(Value::Null.get_first_of(&["colour"]), None);
+}
+
+#[test]
+fn test_set_path() {
+    let mut value = Value::new_object();
+    value.set_path("a.b.c", Value::Number(5.0));
+    assert_eq!(
+        value,
+        crate::parse_str(r#"{"a": {"b": {"c": 5}}}"#).unwrap()
+    );
+
+    let mut value = crate::parse_str(r#"{"a": "x", "list": [1, {"k": 2}], "0": 1}"#).unwrap();
+    value.set_path("a.b", Value::Bool(true));
+    value.set_path("list.1.k", Value::Null);
+    value.set_path("list.3", Value::Number(4.0));
+    value.set_path("0", Value::Number(2.0));
+    assert_eq!(
+        value,
+        crate::parse_str(r#"{"a": {"b": true}, "list": [1, {"k": null}, null, 4], "0": 2}"#)
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_canonical_json_bytes() {
+    let a =
+        crate::parse_str(r#"{"b": [1.0, -0.0, "\u00e9\t"], "a": {"y": null, "x": 2.5}}"#).unwrap();
+    let b = crate::parse_str(r#"{"a": {"x": 25e-1, "y": null}, "b": [1, 0, "é\u0009"]}"#).unwrap();
+    assert_eq!(a.canonical_json_bytes(), b.canonical_json_bytes());
+    assert_eq!(
+        a.canonical_json_bytes(),
+        r#"{"a":{"x":2.5,"y":null},"b":[1,0,"é\t"]}"#.as_bytes()
+    );
+    assert_ne!(
+        a.canonical_json_bytes(),
+        crate::parse_str(r#"{"a": 1}"#)
+            .unwrap()
+            .canonical_json_bytes()
+    );
+}
+
+#[test]
+fn test_zip_arrays() {
+    let numbers = crate::parse_str(r#"[1, 2]"#).unwrap();
+    let letters = crate::parse_str(r#"["a", "b"]"#).unwrap();
+    assert_eq!(
+        Value::zip_arrays(&numbers, &letters),
+        Some(crate::parse_str(r#"[[1, "a"], [2, "b"]]"#).unwrap())
+    );
+    assert_eq!(
+        Value::zip_arrays(&Value::new_array(), &Value::new_array()),
+        Some(Value::new_array())
+    );
+    assert_eq!(Value::zip_arrays(&numbers, &Value::new_array()), None);
+    assert_eq!(Value::zip_arrays(&numbers, &Value::Null), None);
+}
@@ -0,0 +1,78 @@
+use crate::error::JsonError;
+use crate::lexer::Lexer;
+use crate::parser::{Parser, ParserOptions};
+
+/// A scalar field value borrowed from the tokens, so visiting doesn't build any
+/// `Value`
+#[derive(Debug, PartialEq)]
+pub enum Scalar<'t> {
+    Str(&'t str),
+    Number(f64),
+    Bool(bool),
+    Null,
+}
+
+/// Receives the fields picked out by [`deserialize_struct`]
+pub trait FieldVisitor {
+    fn visit_field(&mut self, field: &str, value: Scalar<'_>);
+}
+
+/// Parses `input`, which must be an object, and calls `visitor` for every
+/// top-level field whose key is in `fields`. Other fields are only checked for
+/// syntax, and a listed field with an array or object value is an error
+pub fn deserialize_struct(
+    input: &str,
+    fields: &[&str],
+    visitor: &mut impl FieldVisitor,
+) -> Result<(), JsonError> {
+    let mut lexer = Lexer::new(input);
+    lexer.lex()?;
+    Parser::from_lexer(lexer, ParserOptions::default()).visit_fields(fields, visitor)?;
+    Ok(())
+}
+
+#[test]
+fn test_deserialize_struct() {
+    #[derive(Default)]
+    struct Person {
+        name: String,
+        age: f64,
+        is_student: bool,
+    }
+
+    impl FieldVisitor for Person {
+        fn visit_field(&mut self, field: &str, value: Scalar<'_>) {
+            match (field, value) {
+                ("name", Scalar::Str(s)) => self.name = s.to_string(),
+                ("age", Scalar::Number(n)) => self.age = n,
+                ("is_student", Scalar::Bool(b)) => self.is_student = b,
+                _ => {}
+            }
+        }
+    }
+
+    let source = r#"
+    {
+        "name": "Alice",
+        "scores": [95.5, 88.0, {"nested": [76]}],
+        "age": 30,
+        "address": null,
+        "is_student": true
+    }
+    "#;
+    let mut person = Person::default();
+    deserialize_struct(source, &["name", "age", "is_student"], &mut person).unwrap();
+    let Person {
+        name,
+        age,
+        is_student,
+    } = person;
+    assert_eq!(name, "Alice");
+    assert_eq!(age, 30.0);
+    assert!(is_student);
+
+    let mut person = Person::default();
+    assert!(deserialize_struct(source, &["scores"], &mut person).is_err());
+    assert!(deserialize_struct("[1]", &["name"], &mut person).is_err());
+    assert!(deserialize_struct(r#"{"name": "a",}"#, &["name"], &mut person).is_err());
+}